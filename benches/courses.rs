@@ -0,0 +1,128 @@
+//! Box, radius and nearest lookups on synthetic courses, linear scan against the
+//! spatial index. `cargo bench --bench courses [-- COUNT]`, 50,000 courses by default.
+//!
+//! 50,000 courses spread over the continental US, 1,000 queries each, on a release
+//! build (October 2026, single-core Linux VM):
+//!
+//! ```text
+//! Indexed 50000 courses in 64.510261ms
+//! box 0.5°       linear   307.36ms  indexed     2.61ms  (118x, 9206 vs 9206 hits over 1000 queries)
+//! radius 25 km   linear      2.90s  indexed     3.80ms  (763x, 7465 vs 7465 hits over 1000 queries)
+//! nearest 10     linear      5.14s  indexed     7.48ms  (687x, 10000 vs 10000 hits over 1000 queries)
+//! ```
+
+use std::time::Instant;
+
+use tee_time_tracker_v2::course_service::{courses, geo};
+use tee_time_tracker_v2::structs::{Coords, CourseMetadata, CpsId, GolfCourse, Provider};
+
+fn main() {
+    let count = std::env::args()
+        .skip(1)
+        .find(|arg| !arg.starts_with("--"))
+        .map(|arg| arg.parse::<usize>().expect("course count must be a whole number"))
+        .unwrap_or(50_000);
+
+    run(count);
+}
+
+fn run(count: usize) {
+    let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+    let courses: Vec<GolfCourse> = (0..count)
+        .map(|i| synthetic_course(i, rng.range(25.0, 49.0), rng.range(-124.0, -67.0)))
+        .collect();
+
+    let started = Instant::now();
+    let catalog = courses::build_catalog(courses);
+    println!("Indexed {} courses in {:?}", count, started.elapsed());
+
+    let centers: Vec<(f64, f64)> = (0..1000)
+        .map(|_| (rng.range(25.0, 49.0), rng.range(-124.0, -67.0)))
+        .collect();
+    let all = &catalog.courses;
+
+    report("box 0.5°", &centers, |&(lat, lon)| {
+        all.iter()
+            .filter(|c| (lat - 0.25..=lat + 0.25).contains(&c.lat) && (lon - 0.25..=lon + 0.25).contains(&c.lon))
+            .count()
+    }, |&(lat, lon)| {
+        let coords = Coords {
+            min_lat: lat - 0.25,
+            max_lat: lat + 0.25,
+            min_lon: lon - 0.25,
+            max_lon: lon + 0.25,
+        };
+        courses::get_courses(&catalog, Some(coords), courses::Listing::All).len()
+    });
+
+    report("radius 25 km", &centers, |&(lat, lon)| {
+        all.iter()
+            .filter(|c| geo::haversine_km(lat, lon, c.lat, c.lon) <= 25.0)
+            .count()
+    }, |&center| courses::courses_within(&catalog, center, 25.0, courses::Listing::All).len());
+
+    report("nearest 10", &centers, |&(lat, lon)| {
+        let mut distances: Vec<f64> = all
+            .iter()
+            .map(|c| geo::haversine_km(lat, lon, c.lat, c.lon))
+            .collect();
+        distances.sort_by(|a, b| a.total_cmp(b));
+        distances.truncate(10);
+        distances.len()
+    }, |&center| courses::nearest_courses(&catalog, center, 10, courses::Listing::All).len());
+}
+
+fn report(
+    label: &str,
+    centers: &[(f64, f64)],
+    linear: impl Fn(&(f64, f64)) -> usize,
+    indexed: impl Fn(&(f64, f64)) -> usize,
+) {
+    let started = Instant::now();
+    let linear_hits: usize = centers.iter().map(&linear).sum();
+    let linear_time = started.elapsed();
+
+    let started = Instant::now();
+    let indexed_hits: usize = centers.iter().map(&indexed).sum();
+    let indexed_time = started.elapsed();
+
+    println!(
+        "{:<14} linear {:>10.2?}  indexed {:>10.2?}  ({:.0}x, {} vs {} hits over {} queries)",
+        label,
+        linear_time,
+        indexed_time,
+        linear_time.as_secs_f64() / indexed_time.as_secs_f64().max(f64::EPSILON),
+        linear_hits,
+        indexed_hits,
+        centers.len()
+    );
+}
+
+fn synthetic_course(i: usize, lat: f64, lon: f64) -> GolfCourse {
+    GolfCourse {
+        slug: format!("bench-{}", i),
+        name: format!("Bench Course {}", i),
+        aliases: Vec::new(),
+        lat,
+        lon,
+        timezone: None,
+        enabled: true,
+        hole_options: Vec::new(),
+        season: None,
+        closures: Vec::new(),
+        metadata: CourseMetadata::default(),
+        provider: Provider::Cps(CpsId(format!("bench-{}", i))),
+    }
+}
+
+/// Small deterministic generator so runs are comparable.
+struct XorShift(u64);
+
+impl XorShift {
+    fn range(&mut self, min: f64, max: f64) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        min + (self.0 >> 11) as f64 / (1u64 << 53) as f64 * (max - min)
+    }
+}
@@ -1,19 +1,221 @@
 use std::fs;
+use std::env;
+use std::sync::Arc;
+use std::path::PathBuf;
+use std::ops::Range;
+use std::time::{Duration, SystemTime};
+use std::collections::{HashMap, HashSet};
+
+use serde_json::Value;
+use chrono::NaiveDate;
+use rstar::{RTree, AABB};
 
 use crate::structs::{
+    AppState,
+    Area,
+    BoxPoint,
+    CourseCatalog,
     GolfCourse,
     Coords,
+    CourseIssue,
+    CourseFilter,
+    CourseState,
+    CourseStatus,
+    CatalogSource,
+    Provider,
+    ApiError,
+    Ring,
+    SpherePoint,
+    slugify,
 };
 
 pub mod courses {
     pub use super::*;
 
-    pub fn load_courses() -> Vec<GolfCourse> {
-        let data = fs::read_to_string("src/resources/golf_courses.json")
-            .expect("Failed to read golf_courses.json");
+    pub const BUILTIN_COURSES: &str = include_str!("resources/golf_courses.json");
+
+    /// Catalog files or directories, separated like `PATH`. `--catalog` flags take precedence.
+    pub const CATALOG_ENV: &str = "COURSE_CATALOG";
+
+    /// Resolves where to load courses from: `--catalog` paths if given, else `COURSE_CATALOG`,
+    /// else the built-in catalog. Directories expand to their `*.json` files in name order.
+    pub fn catalog_sources(cli_paths: &[PathBuf]) -> Result<Vec<CatalogSource>, String> {
+        let paths: Vec<PathBuf> = if !cli_paths.is_empty() {
+            cli_paths.to_vec()
+        } else if let Some(value) = env::var_os(CATALOG_ENV) {
+            env::split_paths(&value)
+                .filter(|p| !p.as_os_str().is_empty())
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        if paths.is_empty() {
+            return Ok(vec![CatalogSource::Builtin]);
+        }
+
+        let mut sources = Vec::new();
+        for path in paths {
+            if !path.is_dir() {
+                sources.push(CatalogSource::File(path));
+                continue;
+            }
+
+            let mut files: Vec<PathBuf> = fs::read_dir(&path)
+                .map_err(|e| format!("{}: {}", path.display(), e))?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "json"))
+                .collect();
+            files.sort();
+
+            if files.is_empty() {
+                return Err(format!("{}: no .json catalog files in directory", path.display()));
+            }
+            sources.extend(files.into_iter().map(CatalogSource::File));
+        }
+
+        Ok(sources)
+    }
+
+    /// Loads and merges catalogs in order. A course defined again in a later catalog
+    /// (same provider id) replaces the earlier definition; within one catalog it is an error.
+    pub fn read_catalog(sources: &[CatalogSource]) -> Result<Vec<GolfCourse>, Vec<String>> {
+        let entries = read_catalog_entries(sources)?;
+
+        Ok(entries.into_iter().map(|(course, _)| course).collect())
+    }
+
+    /// A course and the catalog that defines it.
+    pub type CatalogEntry = (GolfCourse, CatalogSource);
+
+    /// Like `read_catalog`, also returning which catalog each course came from.
+    pub fn read_catalog_entries(sources: &[CatalogSource]) -> Result<Vec<CatalogEntry>, Vec<String>> {
+        let mut merged: Vec<CatalogEntry> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        let mut errors = Vec::new();
+
+        for source in sources {
+            let data = match source {
+                CatalogSource::Builtin => BUILTIN_COURSES.to_string(),
+                CatalogSource::File(path) => match fs::read_to_string(path) {
+                    Ok(d) => d,
+                    Err(e) => {
+                        errors.push(format!("{}: {}", source, e));
+                        continue;
+                    }
+                },
+            };
+
+            let courses = match validation::validate_catalog(&data) {
+                Ok(c) => c,
+                Err(issues) => {
+                    errors.extend(issues.iter().map(|issue| format!("{}: {}", source, issue)));
+                    continue;
+                }
+            };
+
+            for course in courses {
+                match index.get(&course.provider.key()) {
+                    Some(&i) => {
+                        println!(
+                            "{}: {:?} overrides the definition from {}",
+                            source, course.name, merged[i].1
+                        );
+                        merged[i] = (course, source.clone());
+                    }
+                    None => {
+                        index.insert(course.provider.key(), merged.len());
+                        merged.push((course, source.clone()));
+                    }
+                }
+            }
+        }
+
+        let mut slugs: HashMap<&str, &CatalogSource> = HashMap::new();
+        for (course, source) in &merged {
+            if let Some(first) = slugs.insert(&course.slug, source) {
+                errors.push(format!(
+                    "{}: slug {:?} ({}) is already used by a different course in {}",
+                    source, course.slug, course.name, first
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(merged)
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn load_courses(sources: &[CatalogSource]) -> Vec<GolfCourse> {
+        // Misconfigured courses should stop startup, not show up as empty results later
+        match read_catalog(sources) {
+            Ok(courses) => courses,
+            Err(errors) => {
+                for error in &errors {
+                    eprintln!("{}", error);
+                }
+                eprintln!("Course catalog has {} problem(s), refusing to start", errors.len());
+                std::process::exit(1);
+            }
+        }
+    }
+
+    /// Re-reads the configured catalogs and swaps them in. On any problem the
+    /// current catalog stays live and the problems are returned.
+    pub fn reload_courses(state: &AppState) -> Result<usize, Vec<String>> {
+        let sources = catalog_sources(&state.catalog_paths).map_err(|e| vec![e])?;
+        let courses = read_catalog(&sources)?;
+        let count = courses.len();
+
+        *state.courses.write().expect("course catalog lock poisoned") = Arc::new(build_catalog(courses));
+
+        Ok(count)
+    }
+
+    type Fingerprint = Vec<(PathBuf, Option<SystemTime>, Option<u64>)>;
+
+    fn catalog_fingerprint(paths: &[PathBuf]) -> Fingerprint {
+        catalog_sources(paths)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|source| match source {
+                CatalogSource::File(path) => {
+                    let meta = fs::metadata(&path).ok();
+                    let modified = meta.as_ref().and_then(|m| m.modified().ok());
+                    let len = meta.map(|m| m.len());
+                    Some((path, modified, len))
+                }
+                CatalogSource::Builtin => None,
+            })
+            .collect()
+    }
+
+    /// Polls the catalog files and reloads when any of them change.
+    pub async fn watch_catalog(state: AppState, every: Duration) {
+        let mut last = catalog_fingerprint(&state.catalog_paths);
+        let mut ticker = tokio::time::interval(every);
+
+        loop {
+            ticker.tick().await;
+
+            let current = catalog_fingerprint(&state.catalog_paths);
+            if current == last {
+                continue;
+            }
+            last = current;
 
-        serde_json::from_str(&data)
-            .expect("Invalid JSON format")
+            match reload_courses(&state) {
+                Ok(count) => println!("Course catalog changed, reloaded {} courses", count),
+                Err(errors) => {
+                    for error in &errors {
+                        eprintln!("{}", error);
+                    }
+                    eprintln!("Course catalog change rejected, keeping the previous catalog");
+                }
+            }
+        }
     }
 
     pub fn default_coords() -> Coords {
@@ -24,21 +226,1162 @@ pub mod courses {
             max_lon: -94.235,
         }
     }
-    
-    pub fn get_courses<'a>(
-        courses: &'a [GolfCourse],
+
+    /// Why a course is closed on `date`, or `None` if it is open.
+    pub fn closure_reason(course: &GolfCourse, date: NaiveDate) -> Option<String> {
+        if !course.enabled {
+            return Some("disabled".to_string());
+        }
+
+        if course.closures.contains(&date) {
+            return Some(format!("closed on {}", date));
+        }
+
+        match &course.season {
+            Some(season) if !season.contains(date) => Some(format!(
+                "out of season (open {} to {})",
+                String::from(season.open),
+                String::from(season.close)
+            )),
+            _ => None,
+        }
+    }
+
+    /// Used instead of the default region when searching by name.
+    pub fn world_coords() -> Coords {
+        Coords {
+            min_lat: -90.0,
+            max_lat: 90.0,
+            min_lon: -180.0,
+            max_lon: 180.0,
+        }
+    }
+
+    /// Splits out the courses that are open on `date`, with a status for every course.
+    pub fn open_courses(
+        courses: Vec<&GolfCourse>,
+        date: NaiveDate,
+    ) -> (Vec<&GolfCourse>, Vec<CourseStatus>) {
+        let mut open = Vec::new();
+        let mut statuses = Vec::new();
+
+        for course in courses {
+            let reason = closure_reason(course, date);
+            let status = if reason.is_some() { CourseState::Closed } else { CourseState::Open };
+
+            statuses.push(CourseStatus {
+                slug: course.slug.clone(),
+                course: course.name.clone(),
+                date,
+                status,
+                reason,
+            });
+
+            if status == CourseState::Open {
+                open.push(course);
+            }
+        }
+
+        (open, statuses)
+    }
+
+    /// Each course open on each date, to fetch tee sheets for.
+    pub fn open_sheets(courses: &[&GolfCourse], dates: &[NaiveDate]) -> Vec<(GolfCourse, NaiveDate)> {
+        dates
+            .iter()
+            .flat_map(|&date| {
+                open_courses(courses.to_vec(), date)
+                    .0
+                    .into_iter()
+                    .map(move |course| (course.clone(), date))
+            })
+            .collect()
+    }
+
+    pub fn matches_filter(course: &GolfCourse, filter: &CourseFilter) -> bool {
+        let metadata = &course.metadata;
+
+        filter.walkable.is_none_or(|w| metadata.walking_allowed == Some(w))
+            && filter.driving_range.is_none_or(|d| metadata.driving_range == Some(d))
+            && filter.access.is_none_or(|a| metadata.access == Some(a))
+            && filter.holes.is_none_or(|h| metadata.holes == Some(h))
+            && filter.round_length.is_none_or(|h| course.hole_options.is_empty() || course.hole_options.contains(&h))
+    }
+
+    /// Indexes courses by slug and position.
+    pub fn build_catalog(courses: Vec<GolfCourse>) -> CourseCatalog {
+        let by_slug = courses
+            .iter()
+            .enumerate()
+            .map(|(i, course)| (course.slug.clone(), i))
+            .collect();
+
+        let box_index = RTree::bulk_load(
+            courses
+                .iter()
+                .enumerate()
+                .map(|(i, course)| BoxPoint::new([course.lon, course.lat], i))
+                .collect(),
+        );
+
+        let sphere_index = RTree::bulk_load(
+            courses
+                .iter()
+                .enumerate()
+                .map(|(i, course)| SpherePoint::new(geo::unit_vector(course.lat, course.lon), i))
+                .collect(),
+        );
+
+        CourseCatalog {
+            courses,
+            by_slug,
+            box_index,
+            sphere_index,
+        }
+    }
+
+    pub fn find_course<'a>(catalog: &'a CourseCatalog, slug: &str) -> Option<&'a GolfCourse> {
+        catalog.by_slug.get(slug).map(|&i| &catalog.courses[i])
+    }
+
+    /// Which courses a location search keeps.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Listing {
+        /// Enabled courses, for plain course listings.
+        Enabled,
+        /// Courses open that day, which leaves out disabled ones too.
+        OpenOn(NaiveDate),
+        /// Every course, so tee time searches can report disabled ones as closed
+        /// instead of leaving them out silently.
+        All,
+    }
+
+    fn is_listed(course: &GolfCourse, listing: Listing) -> bool {
+        match listing {
+            Listing::Enabled => course.enabled,
+            Listing::OpenOn(date) => closure_reason(course, date).is_none(),
+            Listing::All => true,
+        }
+    }
+
+    /// Courses in `coords` that `listing` keeps.
+    pub fn get_courses(
+        catalog: &CourseCatalog,
         coords: Option<Coords>,
-    ) -> Vec<&'a GolfCourse> {
+        listing: Listing,
+    ) -> Vec<&GolfCourse> {
         let coords = coords.unwrap_or_else(default_coords);
 
-        courses
-            .iter()
-            .filter(|course| {
-                coords.min_lat <= course.lat &&
-                course.lat <= coords.max_lat &&
-                coords.min_lon <= course.lon &&
-                course.lon <= coords.max_lon
+        // Keep catalog order so results don't depend on the tree layout
+        let mut found: Vec<usize> = coords
+            .corners()
+            .into_iter()
+            .flat_map(|(lower, upper)| {
+                catalog
+                    .box_index
+                    .locate_in_envelope(&AABB::from_corners(lower, upper))
+                    .map(|point| point.data)
             })
+            .collect();
+        // Back to catalog order across the two halves of a wrapping box, each course once
+        found.sort_unstable();
+        found.dedup();
+
+        found
+            .into_iter()
+            .map(|i| &catalog.courses[i])
+            .filter(|course| is_listed(course, listing))
+            .collect()
+    }
+
+    /// Courses within `radius_km` of `(lat, lon)`, nearest first, with their distance in km.
+    pub fn courses_within(
+        catalog: &CourseCatalog,
+        (lat, lon): (f64, f64),
+        radius_km: f64,
+        listing: Listing,
+    ) -> Vec<(&GolfCourse, f64)> {
+        let chord = geo::chord_length(radius_km);
+
+        let mut found: Vec<(&GolfCourse, f64)> = catalog
+            .sphere_index
+            .locate_within_distance(geo::unit_vector(lat, lon), chord * chord)
+            .map(|point| &catalog.courses[point.data])
+            .filter(|course| is_listed(course, listing))
+            .map(|course| (course, geo::haversine_km(lat, lon, course.lat, course.lon)))
+            .collect();
+
+        found.sort_by(|a, b| a.1.total_cmp(&b.1));
+        found
+    }
+
+    /// Courses inside `area`, in catalog order. The index narrows it to the area's
+    /// bounding box first, so only those courses are tested against the polygons.
+    pub fn courses_in_area<'a>(
+        catalog: &'a CourseCatalog,
+        area: &Area,
+        listing: Listing,
+    ) -> Vec<&'a GolfCourse> {
+        get_courses(catalog, Some(area.bounds()), listing)
+            .into_iter()
+            .filter(|course| geo::area_contains(area, course.lat, course.lon))
+            .collect()
+    }
+
+    /// Groups courses that share a grid cell at `zoom`, in order of each group's first course.
+    pub fn cluster_courses(courses: Vec<&GolfCourse>, zoom: u32) -> Vec<Vec<&GolfCourse>> {
+        let mut clusters: Vec<Vec<&GolfCourse>> = Vec::new();
+        let mut cells: HashMap<(u64, u64), usize> = HashMap::new();
+
+        for course in courses {
+            let cell = geo::grid_cell(course.lat, course.lon, zoom);
+            match cells.get(&cell) {
+                Some(&i) => clusters[i].push(course),
+                None => {
+                    cells.insert(cell, clusters.len());
+                    clusters.push(vec![course]);
+                }
+            }
+        }
+
+        clusters
+    }
+
+    /// The `k` courses nearest to `(lat, lon)`, nearest first, with their distance in km.
+    pub fn nearest_courses(
+        catalog: &CourseCatalog,
+        (lat, lon): (f64, f64),
+        k: usize,
+        listing: Listing,
+    ) -> Vec<(&GolfCourse, f64)> {
+        catalog
+            .sphere_index
+            .nearest_neighbor_iter(&geo::unit_vector(lat, lon))
+            .map(|point| &catalog.courses[point.data])
+            .filter(|course| is_listed(course, listing))
+            .take(k)
+            .map(|course| (course, geo::haversine_km(lat, lon, course.lat, course.lon)))
             .collect()
     }
-}
\ No newline at end of file
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn catalog(positions: &[(&str, f64, f64)]) -> CourseCatalog {
+            build_catalog(
+                positions
+                    .iter()
+                    .map(|&(slug, lat, lon)| {
+                        let mut course: GolfCourse = serde_json::from_value(serde_json::json!({
+                            "name": slug,
+                            "lat": lat,
+                            "lon": lon,
+                            "source": "bookateetime",
+                            "id": slug,
+                        }))
+                        .expect("test course is valid");
+                        course.slug = slug.to_string();
+                        course
+                    })
+                    .collect(),
+            )
+        }
+
+        fn slugs(courses: Vec<&GolfCourse>) -> Vec<&str> {
+            courses.into_iter().map(|course| course.slug.as_str()).collect()
+        }
+
+        #[test]
+        fn a_box_across_the_antimeridian_finds_both_sides_once() {
+            let catalog = catalog(&[
+                ("fiji", -17.7, 179.5),
+                ("greenwich", 51.5, 0.0),
+                ("samoa", -13.8, -179.5),
+                ("east-edge", -15.0, 180.0),
+                ("west-edge", -15.0, -180.0),
+            ]);
+            let coords = Coords { min_lat: -20.0, max_lat: -10.0, min_lon: 179.0, max_lon: -179.0 };
+
+            assert_eq!(coords.corners().len(), 2);
+            assert_eq!(
+                slugs(get_courses(&catalog, Some(coords), Listing::All)),
+                ["fiji", "samoa", "east-edge", "west-edge"],
+            );
+        }
+
+        #[test]
+        fn a_box_that_doesnt_wrap_stays_on_its_side() {
+            let catalog = catalog(&[("fiji", -17.7, 179.5), ("samoa", -13.8, -179.5)]);
+            let coords = Coords { min_lat: -20.0, max_lat: -10.0, min_lon: -179.9, max_lon: 179.0 };
+
+            assert_eq!(coords.corners().len(), 1);
+            assert_eq!(slugs(get_courses(&catalog, Some(coords), Listing::All)), ["samoa"]);
+        }
+    }
+}
+
+pub mod geo {
+    use super::*;
+
+    pub const EARTH_RADIUS_KM: f64 = 6371.0;
+
+    pub fn unit_vector(lat: f64, lon: f64) -> [f64; 3] {
+        let (lat, lon) = (lat.to_radians(), lon.to_radians());
+
+        [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+    }
+
+    /// Straight-line distance between unit vectors that are `km` apart on the surface.
+    pub fn chord_length(km: f64) -> f64 {
+        let angle = (km / EARTH_RADIUS_KM).min(std::f64::consts::PI);
+
+        2.0 * (angle / 2.0).sin()
+    }
+
+    pub fn haversine_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+        let d_lat = (lat2 - lat1).to_radians();
+        let d_lon = (lon2 - lon1).to_radians();
+        let a = (d_lat / 2.0).sin().powi(2)
+            + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
+
+        2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+
+    /// Web Mercator grid cell of a point, `CELLS_PER_TILE` cells across each map tile
+    /// at `zoom`, so clusters are roughly the same size on screen at any zoom.
+    pub fn grid_cell(lat: f64, lon: f64, zoom: u32) -> (u64, u64) {
+        const CELLS_PER_TILE: f64 = 4.0;
+
+        // Mercator stretches to infinity at the poles; tiles stop at about 85.05°
+        let lat = lat.clamp(-85.05, 85.05).to_radians();
+        let x = (lon + 180.0) / 360.0;
+        let y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / std::f64::consts::PI) / 2.0;
+        let cells = 2f64.powi(zoom as i32) * CELLS_PER_TILE;
+
+        let cell = |v: f64| (v * cells).floor().clamp(0.0, cells - 1.0) as u64;
+        (cell(x), cell(y))
+    }
+
+    /// Ray casting: counts how many ring edges a ray east from the point crosses.
+    pub fn ring_contains(ring: &Ring, lat: f64, lon: f64) -> bool {
+        let mut inside = false;
+
+        for edge in ring.windows(2) {
+            let ([x1, y1], [x2, y2]) = (edge[0], edge[1]);
+            if (y1 > lat) != (y2 > lat) && lon < x1 + (lat - y1) * (x2 - x1) / (y2 - y1) {
+                inside = !inside;
+            }
+        }
+
+        inside
+    }
+
+    /// Inside the outer ring of some polygon and outside all of its holes.
+    pub fn area_contains(area: &Area, lat: f64, lon: f64) -> bool {
+        area.polygons().into_iter().any(|polygon| match polygon.split_first() {
+            Some((outer, holes)) => {
+                ring_contains(outer, lat, lon) && !holes.iter().any(|hole| ring_contains(hole, lat, lon))
+            }
+            None => false,
+        })
+    }
+}
+
+pub mod search {
+    use super::*;
+
+    /// Splits a name into lowercase words, dropping apostrophes so "Dub's" reads as "dubs".
+    pub fn tokenize(text: &str) -> Vec<String> {
+        text.to_lowercase()
+            .replace(['\'', '\u{2019}'], "")
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// How far a query word may be from a name word: exact for short words,
+    /// one typo up to seven letters, two beyond that.
+    fn allowed_typos(word: &str) -> usize {
+        match word.chars().count() {
+            0..=3 => 0,
+            4..=7 => 1,
+            _ => 2,
+        }
+    }
+
+    fn levenshtein(a: &str, b: &str) -> usize {
+        let b: Vec<char> = b.chars().collect();
+        let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+        for (i, ca) in a.chars().enumerate() {
+            let mut current = vec![i + 1; b.len() + 1];
+            for (j, cb) in b.iter().enumerate() {
+                let substitution = previous[j] + usize::from(ca != *cb);
+                current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+            }
+            previous = current;
+        }
+
+        previous[b.len()]
+    }
+
+    /// Cost of matching one query word against a name, or `None` if no word is close.
+    /// Prefixes count as a match so partially typed names still find the course.
+    fn word_cost(query_word: &str, name_words: &[String]) -> Option<usize> {
+        name_words
+            .iter()
+            .filter_map(|word| {
+                if word == query_word {
+                    Some(0)
+                } else if query_word.len() >= 2 && word.starts_with(query_word) {
+                    Some(1)
+                } else {
+                    let distance = levenshtein(query_word, word);
+                    (distance <= allowed_typos(query_word)).then_some(distance + 1)
+                }
+            })
+            .min()
+    }
+
+    /// Case-insensitive, typo-tolerant match of `query` against a course's name and
+    /// aliases. Every query word must match; lower scores are better matches.
+    pub fn name_score(course: &GolfCourse, query: &[String]) -> Option<usize> {
+        std::iter::once(&course.name)
+            .chain(course.aliases.iter())
+            .filter_map(|name| {
+                let words = tokenize(name);
+                query
+                    .iter()
+                    .map(|q| word_cost(q, &words))
+                    .sum::<Option<usize>>()
+            })
+            .min()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn course(name: &str, aliases: &[&str]) -> GolfCourse {
+            serde_json::from_value(serde_json::json!({
+                "name": name,
+                "aliases": aliases,
+                "lat": 39.1,
+                "lon": -94.6,
+                "source": "bookateetime",
+                "id": "118-1",
+            }))
+            .expect("test course is valid")
+        }
+
+        fn matches(query: &str, course: &GolfCourse) -> bool {
+            name_score(course, &tokenize(query)).is_some()
+        }
+
+        #[test]
+        fn tokenize_drops_case_apostrophes_and_punctuation() {
+            assert_eq!(tokenize("Dub's Dread Golf Club"), ["dubs", "dread", "golf", "club"]);
+            assert_eq!(tokenize("Paradise Pointe Golf Course - The Outlaw"), ["paradise", "pointe", "golf", "course", "the", "outlaw"]);
+        }
+
+        #[test]
+        fn a_shared_name_matches_every_course_that_has_it() {
+            let outlaw = course("Paradise Pointe Golf Course - The Outlaw", &[]);
+            let posse = course("Paradise Pointe Golf Course - The Posse", &[]);
+            let dubs = course("Dub's Dread Golf Club", &[]);
+
+            assert!(matches("Paradise Pointe", &outlaw));
+            assert!(matches("Paradise Pointe", &posse));
+            assert!(!matches("Paradise Pointe", &dubs));
+            assert!(matches("paradise outlaw", &outlaw));
+            assert!(!matches("paradise outlaw", &posse));
+        }
+
+        #[test]
+        fn apostrophes_and_case_dont_matter() {
+            let dubs = course("Dub's Dread Golf Club", &[]);
+
+            assert!(matches("dubs dread", &dubs));
+            assert!(matches("DUB'S DREAD", &dubs));
+        }
+
+        #[test]
+        fn typos_and_prefixes_match_but_score_worse() {
+            let swope = course("Swope Memorial Golf Course", &[]);
+            let exact = name_score(&swope, &tokenize("swope memorial")).unwrap();
+
+            assert!(name_score(&swope, &tokenize("swope memorail")).unwrap() > exact);
+            assert!(name_score(&swope, &tokenize("swope mem")).unwrap() > exact);
+            // Short words must be exact
+            assert!(!matches("swope gof", &swope));
+            assert!(!matches("swope memorial park", &swope));
+        }
+
+        #[test]
+        fn aliases_match_too() {
+            let dubs = course("Dub's Dread Golf Club", &["Dubs"]);
+
+            assert!(matches("dubs", &dubs));
+            assert_eq!(name_score(&dubs, &tokenize("dubs")), Some(0));
+        }
+    }
+}
+
+pub mod validation {
+    use super::*;
+    use chrono_tz::Tz;
+    use serde::de::DeserializeOwned;
+    use crate::structs::{CourseAccess, CourseMetadata, MonthDay, Season, Tee};
+
+    /// Slugs that would be shadowed by fixed routes under `/courses/`.
+    pub const RESERVED_SLUGS: [&str; 1] = ["clusters"];
+
+    /// Parses a course catalog and checks every entry, collecting all problems
+    /// instead of stopping at the first one.
+    pub fn validate_catalog(data: &str) -> Result<Vec<GolfCourse>, Vec<CourseIssue>> {
+        let raw: Value = match serde_json::from_str(data) {
+            Ok(v) => v,
+            Err(e) => return Err(vec![issue("$", None, e.to_string())]),
+        };
+
+        let entries = match raw.as_array() {
+            Some(a) => a,
+            None => return Err(vec![issue("$", None, "expected an array of courses".to_string())]),
+        };
+
+        let mut issues = Vec::new();
+        let mut courses = Vec::new();
+        let mut seen_ids: HashMap<String, usize> = HashMap::new();
+        let mut seen_slugs: HashMap<String, usize> = HashMap::new();
+
+        for (i, entry) in entries.iter().enumerate() {
+            let path = format!("$[{}]", i);
+            let name = entry.get("name").and_then(Value::as_str).map(str::to_string);
+            let before = issues.len();
+
+            if !entry.is_object() {
+                issues.push(issue(&path, None, "expected a course object".to_string()));
+                continue;
+            }
+
+            if name.as_deref().is_none_or(|n| n.trim().is_empty()) {
+                issues.push(issue(&format!("{}.name", path), None, "missing course name".to_string()));
+            }
+
+            for (field, limit) in [("lat", 90.0), ("lon", 180.0)] {
+                match entry.get(field).map(|v| v.as_f64()) {
+                    None => issues.push(issue(&format!("{}.{}", path, field), name.clone(), format!("missing {}", field))),
+                    Some(None) => issues.push(issue(&format!("{}.{}", path, field), name.clone(), format!("{} must be a number", field))),
+                    Some(Some(v)) if !(-limit..=limit).contains(&v) => issues.push(issue(
+                        &format!("{}.{}", path, field),
+                        name.clone(),
+                        format!("{} {} is outside -{}..={}", field, v, limit, limit),
+                    )),
+                    Some(Some(_)) => {}
+                }
+            }
+
+            match entry.get("source").map(|v| v.as_str()) {
+                None => issues.push(issue(&format!("{}.source", path), name.clone(), "missing source".to_string())),
+                Some(Some(source)) if Provider::SOURCES.contains(&source) => {
+                    check_provider(entry, &path, &name, &mut issues, &mut seen_ids, i);
+                }
+                Some(source) => issues.push(issue(
+                    &format!("{}.source", path),
+                    name.clone(),
+                    format!(
+                        "unknown source {}, expected one of {}",
+                        source.map(|s| format!("{:?}", s)).unwrap_or_else(|| "(not a string)".to_string()),
+                        Provider::SOURCES.join(", ")
+                    ),
+                )),
+            }
+
+            let slug = match entry.get("slug") {
+                None => name.as_deref().map(slugify).unwrap_or_default(),
+                Some(Value::String(slug)) if !slug.is_empty() && *slug == slugify(slug) => slug.clone(),
+                Some(other) => {
+                    issues.push(issue(
+                        &format!("{}.slug", path),
+                        name.clone(),
+                        format!("slug {} must be lowercase letters, digits and dashes", other),
+                    ));
+                    String::new()
+                }
+            };
+
+            if RESERVED_SLUGS.contains(&slug.as_str()) {
+                issues.push(issue(
+                    &format!("{}.slug", path),
+                    name.clone(),
+                    format!("slug {:?} is taken by the API's own /courses/{} route, set another slug", slug, slug),
+                ));
+            } else if !slug.is_empty() {
+                match seen_slugs.get(&slug) {
+                    Some(first) => issues.push(issue(
+                        &format!("{}.slug", path),
+                        name.clone(),
+                        format!("duplicate slug {:?}, already used by $[{}]", slug, first),
+                    )),
+                    None => {
+                        seen_slugs.insert(slug.clone(), i);
+                    }
+                }
+            }
+
+            let mistyped = check_fields(entry, &path, &name, &mut issues);
+
+            // Range checks on whatever metadata is well typed; the rest is reported above
+            let mut typed = entry.clone();
+            if let Some(fields) = typed.as_object_mut() {
+                fields.retain(|field, _| !mistyped.contains(field));
+            }
+            if let Ok(metadata) = serde_json::from_value::<CourseMetadata>(typed) {
+                for (field, message) in metadata.validate() {
+                    issues.push(issue(&format!("{}.{}", path, field), name.clone(), message));
+                }
+            }
+
+            match serde_json::from_value::<GolfCourse>(entry.clone()) {
+                Ok(mut course) if issues.is_empty() => {
+                    course.slug = slug;
+                    courses.push(course);
+                }
+                Ok(_) => {}
+                // Only what the checks above didn't already place at a field
+                Err(e) if issues.len() == before => issues.push(issue(&path, name, e.to_string())),
+                Err(_) => {}
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(courses)
+        } else {
+            Err(issues)
+        }
+    }
+
+    fn check_provider(
+        entry: &Value,
+        path: &str,
+        name: &Option<String>,
+        issues: &mut Vec<CourseIssue>,
+        seen_ids: &mut HashMap<String, usize>,
+        index: usize,
+    ) {
+        let id_path = format!("{}.id", path);
+        let tagged = serde_json::json!({
+            "source": entry.get("source"),
+            "id": entry.get("id"),
+        });
+
+        let provider: Provider = match serde_json::from_value(tagged) {
+            Ok(p) => p,
+            Err(e) => {
+                issues.push(issue(&id_path, name.clone(), e.to_string()));
+                return;
+            }
+        };
+
+        if let Err(e) = provider.validate() {
+            issues.push(issue(&id_path, name.clone(), e));
+        }
+
+        let key = provider.key();
+        match seen_ids.get(&key) {
+            Some(first) => issues.push(issue(
+                &id_path,
+                name.clone(),
+                format!("duplicate {} id, already used by $[{}]", provider.source(), first),
+            )),
+            None => {
+                seen_ids.insert(key, index);
+            }
+        }
+    }
+
+    /// Checks each optional field against its own type, so a bad value is reported
+    /// at its path, e.g. `$[4].season.open` or `$[4].closures[2]`, next to any others.
+    /// Returns the fields that had problems.
+    fn check_fields(entry: &Value, path: &str, name: &Option<String>, issues: &mut Vec<CourseIssue>) -> HashSet<String> {
+        let mut mistyped = HashSet::new();
+        let mut report = |field: &str, result: Result<(), String>| {
+            if let Err(message) = result {
+                issues.push(issue(&format!("{}.{}", path, field), name.clone(), message));
+                mistyped.extend(field.split(['.', '[']).next().map(str::to_string));
+            }
+        };
+
+        for (field, value) in entry.as_object().into_iter().flatten() {
+            match field.as_str() {
+                "timezone" => report(field, parses::<Tz>(value)),
+                "enabled" | "walking_allowed" | "driving_range" => report(field, parses::<bool>(value)),
+                "holes" | "par" => report(field, parses::<u32>(value)),
+                "access" => report(field, parses::<CourseAccess>(value)),
+                "website" | "phone" | "address" => report(field, parses::<String>(value)),
+                "season" => match value.as_object() {
+                    Some(season) => {
+                        for end in ["open", "close"] {
+                            let result = match season.get(end) {
+                                Some(day) => parses::<MonthDay>(day),
+                                None => Err(format!("missing {}", end)),
+                            };
+                            report(&format!("season.{}", end), result);
+                        }
+                    }
+                    None => report(field, parses::<Season>(value)),
+                },
+                "aliases" | "closures" | "tees" | "hole_options" => {
+                    let Some(items) = value.as_array() else {
+                        report(field, Err(format!("{} must be a list", field)));
+                        continue;
+                    };
+                    if field == "hole_options" && items.is_empty() {
+                        report(field, Err("hole_options must list round lengths between 1 and 36".to_string()));
+                    }
+
+                    for (i, item) in items.iter().enumerate() {
+                        let result = match field.as_str() {
+                            "aliases" => parses::<String>(item),
+                            "closures" => parses::<NaiveDate>(item),
+                            "tees" => parses::<Tee>(item),
+                            _ => match item.as_u64() {
+                                Some(1..=36) => Ok(()),
+                                _ => Err(format!("round length {} must be between 1 and 36", item)),
+                            },
+                        };
+                        report(&format!("{}[{}]", field, i), result);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        mistyped
+    }
+
+    fn parses<T: DeserializeOwned>(value: &Value) -> Result<(), String> {
+        T::deserialize(value).map(|_| ()).map_err(|e| e.to_string())
+    }
+
+    fn issue(path: &str, course: Option<String>, message: String) -> CourseIssue {
+        CourseIssue {
+            path: path.to_string(),
+            course,
+            message,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn paths(catalog: &str) -> Vec<String> {
+            validate_catalog(catalog)
+                .expect_err("catalog is invalid")
+                .into_iter()
+                .map(|issue| issue.path)
+                .collect()
+        }
+
+        #[test]
+        fn reports_every_problem_in_an_entry() {
+            let catalog = r#"[
+                {"id": "118-1", "name": "Shoal Creek", "lat": 39.25, "lon": -94.47, "source": "bookateetime"},
+                {"id": "118-1", "name": "Hodge Park", "lat": 39.25, "lon": -94.51, "source": "bookateetime",
+                 "par": 200, "walking_allowed": "yes"}
+            ]"#;
+
+            assert_eq!(paths(catalog), ["$[1].id", "$[1].walking_allowed", "$[1].par"]);
+        }
+
+        #[test]
+        fn type_errors_carry_their_field_path() {
+            let catalog = r#"[
+                {"id": "118-1", "name": "Shoal Creek", "lat": 39.25, "lon": -94.47, "source": "bookateetime",
+                 "season": {"open": "13-01", "close": "10-31"},
+                 "closures": ["2026-07-04", "July 5th"],
+                 "hole_options": [18, 0]}
+            ]"#;
+
+            assert_eq!(paths(catalog), ["$[0].season.open", "$[0].closures[1]", "$[0].hole_options[1]"]);
+        }
+    }
+}
+
+pub mod admin {
+    use super::*;
+    use axum::http::StatusCode;
+    use serde::Serialize;
+
+    use courses::{catalog_sources, read_catalog_entries, reload_courses, CatalogEntry};
+
+    /// Adds a course to the last configured catalog file.
+    pub async fn add_course(state: &AppState, course: GolfCourse) -> Result<GolfCourse, ApiError> {
+        let _guard = state.catalog_write.lock().await;
+
+        let course = checked(course)?;
+        let (sources, entries) = current_entries(state)?;
+        ensure_unique(&entries, &course, None)?;
+
+        let target = sources
+            .iter()
+            .rev()
+            .find_map(|source| match source {
+                CatalogSource::File(path) => Some(path.clone()),
+                CatalogSource::Builtin => None,
+            })
+            .ok_or_else(read_only)?;
+
+        rewrite_catalog(state, &target, CatalogEdit::Add(&course))?;
+
+        Ok(course)
+    }
+
+    /// Replaces the course with `slug` in the catalog file that defines it.
+    pub async fn update_course(state: &AppState, slug: &str, mut course: GolfCourse) -> Result<GolfCourse, ApiError> {
+        let _guard = state.catalog_write.lock().await;
+
+        // Keep the existing slug unless the edit sets one, so renaming doesn't move the course
+        if course.slug.is_empty() {
+            course.slug = slug.to_string();
+        }
+        let course = checked(course)?;
+
+        let (_, entries) = current_entries(state)?;
+        let path = origin_file(&entries, slug)?;
+        ensure_unique(&entries, &course, Some(slug))?;
+
+        rewrite_catalog(state, &path, CatalogEdit::Replace(slug, &course))?;
+
+        Ok(course)
+    }
+
+    /// Removes the course with `slug` from the catalog file that defines it. If an earlier
+    /// catalog also defines it, that definition becomes live again.
+    pub async fn remove_course(state: &AppState, slug: &str) -> Result<GolfCourse, ApiError> {
+        let _guard = state.catalog_write.lock().await;
+
+        let (_, entries) = current_entries(state)?;
+        let path = origin_file(&entries, slug)?;
+        let removed = entries
+            .iter()
+            .find(|(c, _)| c.slug == slug)
+            .map(|(c, _)| c.clone())
+            .ok_or_else(|| not_found(slug))?;
+
+        rewrite_catalog(state, &path, CatalogEdit::Remove(slug))?;
+
+        Ok(removed)
+    }
+
+    /// Runs a single course through the same validation as the catalog files.
+    fn checked(course: GolfCourse) -> Result<GolfCourse, ApiError> {
+        let data = serde_json::to_string(&[&course])
+            .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e.to_string()))?;
+
+        match validation::validate_catalog(&data) {
+            Ok(mut courses) => Ok(courses.remove(0)),
+            Err(issues) => Err(ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "Invalid course")
+                .with_details(issues.iter().map(|i| i.to_string()).collect())),
+        }
+    }
+
+    fn current_entries(state: &AppState) -> Result<(Vec<CatalogSource>, Vec<CatalogEntry>), ApiError> {
+        let sources = catalog_sources(&state.catalog_paths)
+            .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        let entries = read_catalog_entries(&sources).map_err(|errors| {
+            ApiError::new(StatusCode::CONFLICT, "Catalog files are currently invalid, fix them before editing")
+                .with_details(errors)
+        })?;
+
+        Ok((sources, entries))
+    }
+
+    fn ensure_unique(
+        entries: &[CatalogEntry],
+        course: &GolfCourse,
+        replacing: Option<&str>,
+    ) -> Result<(), ApiError> {
+        let clash = entries.iter().find(|(existing, _)| {
+            Some(existing.slug.as_str()) != replacing
+                && (existing.slug == course.slug || existing.provider.key() == course.provider.key())
+        });
+
+        match clash {
+            Some((existing, _)) => Err(ApiError::new(
+                StatusCode::CONFLICT,
+                format!("Course {:?} already uses this slug or {} id", existing.slug, course.provider.source()),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn origin_file(entries: &[CatalogEntry], slug: &str) -> Result<PathBuf, ApiError> {
+        match entries.iter().find(|(c, _)| c.slug == slug) {
+            Some((_, CatalogSource::File(path))) => Ok(path.clone()),
+            Some((_, CatalogSource::Builtin)) => Err(read_only()),
+            None => Err(not_found(slug)),
+        }
+    }
+
+    /// A change to a single entry of a catalog file.
+    enum CatalogEdit<'a> {
+        Add(&'a GolfCourse),
+        Replace(&'a str, &'a GolfCourse),
+        Remove(&'a str),
+    }
+
+    /// Applies `edit` to one catalog file, writes it atomically and reloads. If the
+    /// merged catalog no longer validates, the file is restored.
+    fn rewrite_catalog(state: &AppState, path: &PathBuf, edit: CatalogEdit) -> Result<(), ApiError> {
+        let internal = |e: String| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e);
+
+        let original = fs::read_to_string(path)
+            .map_err(|e| internal(format!("{}: {}", path.display(), e)))?;
+        let courses = validation::validate_catalog(&original).map_err(|issues| {
+            internal(format!("{}: {} problem(s) in catalog", path.display(), issues.len()))
+        })?;
+
+        let data = edited_catalog(&original, &courses, &edit)
+            .map_err(|e| internal(format!("{}: {}", path.display(), e)))?;
+        write_atomically(path, &data).map_err(|e| internal(format!("{}: {}", path.display(), e)))?;
+
+        if let Err(errors) = reload_courses(state) {
+            let _ = write_atomically(path, &original);
+            return Err(ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "Change would leave the catalog invalid")
+                .with_details(errors));
+        }
+
+        Ok(())
+    }
+
+    /// `original` with only the edited entry's text changed, so hand-written formatting,
+    /// field order and left-out defaults survive in every other entry.
+    fn edited_catalog(original: &str, courses: &[GolfCourse], edit: &CatalogEdit) -> Result<String, String> {
+        let spans = entry_spans(original);
+        if spans.len() != courses.len() {
+            return Err("could not line up catalog entries with their courses".to_string());
+        }
+        let indent = entry_indent(original, &spans);
+        let position = |slug: &str| {
+            courses
+                .iter()
+                .position(|c| c.slug == slug)
+                .ok_or_else(|| format!("no course with slug {:?}", slug))
+        };
+        let splice = |span: Range<usize>, text: &str| {
+            format!("{}{}{}", &original[..span.start], text, &original[span.end..])
+        };
+
+        match *edit {
+            CatalogEdit::Add(course) => {
+                let entry = entry_text(course, None, &indent)?;
+                Ok(match spans.as_slice() {
+                    [] => format!("[\n  {}\n]\n", entry_text(course, None, "  ")?),
+                    [.., before, last] => splice(last.end..last.end, &format!("{}{}", &original[before.end..last.start], entry)),
+                    [last] if indent.is_empty() => splice(last.end..last.end, &format!(",{}", entry)),
+                    [last] => splice(last.end..last.end, &format!(",\n{}{}", indent, entry)),
+                })
+            }
+            CatalogEdit::Replace(slug, course) => {
+                let span = spans[position(slug)?].clone();
+                let existing: Value = serde_json::from_str(&original[span.clone()]).map_err(|e| e.to_string())?;
+                Ok(splice(span, &entry_text(course, Some(&existing), &indent)?))
+            }
+            CatalogEdit::Remove(slug) => {
+                let i = position(slug)?;
+                // Take the separator on one side with it so the array stays well-formed
+                let cut = match (spans.get(i + 1), i.checked_sub(1).map(|j| &spans[j])) {
+                    (Some(next), _) => spans[i].start..next.start,
+                    (None, Some(previous)) => previous.end..spans[i].end,
+                    (None, None) => spans[i].clone(),
+                };
+                Ok(splice(cut, ""))
+            }
+        }
+    }
+
+    /// Byte ranges of the entries in a catalog file's top-level array.
+    fn entry_spans(data: &str) -> Vec<Range<usize>> {
+        let mut spans = Vec::new();
+        let (mut depth, mut in_string, mut escaped) = (0, false, false);
+        let (mut start, mut end) = (None, 0);
+
+        for (i, c) in data.char_indices() {
+            if in_string {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => in_string = false,
+                    _ => {}
+                }
+                end = i + c.len_utf8();
+                continue;
+            }
+            if depth == 1 && matches!(c, ',' | ']') {
+                spans.extend(start.take().map(|start| start..end));
+            }
+            if c.is_whitespace() || (depth == 1 && c == ',') {
+                continue;
+            }
+            if depth == 1 && start.is_none() && c != ']' {
+                start = Some(i);
+            }
+            match c {
+                '"' => in_string = true,
+                '[' | '{' => depth += 1,
+                ']' | '}' => depth -= 1,
+                _ => {}
+            }
+            end = i + c.len_utf8();
+        }
+
+        spans
+    }
+
+    /// Whitespace in front of the first entry on its line, or nothing for a one-line file.
+    fn entry_indent(data: &str, spans: &[Range<usize>]) -> String {
+        let Some(first) = spans.first() else {
+            return "  ".to_string();
+        };
+        let line = &data[..first.start];
+        match line.rfind('\n') {
+            Some(newline) if line[newline + 1..].chars().all(char::is_whitespace) => line[newline + 1..].to_string(),
+            _ => String::new(),
+        }
+    }
+
+    /// `course` as a catalog entry at `indent`. Fields the catalog derives are left out
+    /// unless `existing` spelled them out, and `existing`'s field order is kept.
+    fn entry_text(course: &GolfCourse, existing: Option<&Value>, indent: &str) -> Result<String, String> {
+        let Value::Object(mut fields) = serde_json::to_value(course).map_err(|e| e.to_string())? else {
+            return Err("course did not serialize to an object".to_string());
+        };
+
+        let derived = [
+            ("slug", Value::from(slugify(&course.name))),
+            ("enabled", Value::from(true)),
+        ];
+        for (key, default) in derived {
+            let spelled_out = existing.is_some_and(|e| e.get(key).is_some());
+            if !spelled_out && fields.get(key) == Some(&default) {
+                fields.remove(key);
+            }
+        }
+
+        if let Some(Value::Object(existing)) = existing {
+            let mut ordered = serde_json::Map::new();
+            for key in existing.keys() {
+                if let Some(value) = fields.remove(key) {
+                    ordered.insert(key.clone(), value);
+                }
+            }
+            ordered.append(&mut fields);
+            fields = ordered;
+        }
+
+        let entry = Value::Object(fields);
+        if indent.is_empty() {
+            return serde_json::to_string(&entry).map_err(|e| e.to_string());
+        }
+
+        let mut out = Vec::new();
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+        entry
+            .serialize(&mut serde_json::Serializer::with_formatter(&mut out, formatter))
+            .map_err(|e| e.to_string())?;
+        let text = String::from_utf8(out).map_err(|e| e.to_string())?;
+
+        Ok(text.replace('\n', &format!("\n{}", indent)))
+    }
+
+    fn write_atomically(path: &PathBuf, data: &str) -> std::io::Result<()> {
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, data)?;
+        fs::rename(&tmp, path)
+    }
+
+    fn read_only() -> ApiError {
+        ApiError::new(
+            StatusCode::CONFLICT,
+            "The built-in catalog is read-only; start the server with --catalog to manage courses",
+        )
+    }
+
+    fn not_found(slug: &str) -> ApiError {
+        ApiError::new(StatusCode::NOT_FOUND, format!("No course with slug {:?}", slug))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const CATALOG: &str = r#"[
+    {"id": "118-1", "name": "Shoal Creek", "lat": 39.25, "lon": -94.47, "source": "bookateetime"},
+    {
+        "name": "Hodge Park",
+        "source": "bookateetime",
+        "id": "117-1",
+        "lat": 39.25,
+        "lon": -94.51
+    }
+]
+"#;
+
+        fn courses() -> Vec<GolfCourse> {
+            validation::validate_catalog(CATALOG).expect("test catalog is valid")
+        }
+
+        fn new_course() -> GolfCourse {
+            checked(serde_json::from_str(
+                r#"{"id": "120-1", "name": "Swope Memorial", "lat": 38.99, "lon": -94.52, "source": "bookateetime"}"#,
+            ).expect("new course parses")).expect("new course is valid")
+        }
+
+        #[test]
+        fn adding_appends_only_the_new_entry() {
+            let edited = edited_catalog(CATALOG, &courses(), &CatalogEdit::Add(&new_course())).unwrap();
+
+            assert_eq!(
+                edited,
+                CATALOG.replace(
+                    "-94.51\n    }\n",
+                    "-94.51\n    },\n    {\n        \"id\": \"120-1\",\n        \"name\": \"Swope Memorial\",\n        \"lat\": 38.99,\n        \"lon\": -94.52,\n        \"source\": \"bookateetime\"\n    }\n",
+                ),
+            );
+            assert_eq!(validation::validate_catalog(&edited).unwrap().len(), 3);
+        }
+
+        #[test]
+        fn replacing_keeps_the_other_entries_and_the_field_order() {
+            let mut course = courses().remove(1);
+            course.lat = 39.26;
+
+            let edited = edited_catalog(CATALOG, &courses(), &CatalogEdit::Replace("hodge-park", &course)).unwrap();
+
+            assert_eq!(edited, CATALOG.replace("\"lat\": 39.25,\n", "\"lat\": 39.26,\n"));
+        }
+
+        #[test]
+        fn removing_takes_one_separator_with_the_entry() {
+            let first = edited_catalog(CATALOG, &courses(), &CatalogEdit::Remove("shoal-creek")).unwrap();
+            let last = edited_catalog(CATALOG, &courses(), &CatalogEdit::Remove("hodge-park")).unwrap();
+
+            assert_eq!(&first[..7], "[\n    {");
+            assert_eq!(validation::validate_catalog(&first).unwrap()[0].slug, "hodge-park");
+            assert_eq!(last, "[\n    {\"id\": \"118-1\", \"name\": \"Shoal Creek\", \"lat\": 39.25, \"lon\": -94.47, \"source\": \"bookateetime\"}\n]\n");
+        }
+
+        #[test]
+        fn entry_spans_skip_brackets_inside_strings() {
+            let data = r#"[{"name": "A ], \"{"}, "b" ,[1, 2]]"#;
+
+            let spans: Vec<&str> = entry_spans(data).into_iter().map(|s| &data[s]).collect();
+
+            assert_eq!(spans, [r#"{"name": "A ], \"{"}"#, r#""b""#, "[1, 2]"]);
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod structs;
+pub mod course_service;
+pub mod tee_time_service;
+pub mod watch_service;
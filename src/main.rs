@@ -1,57 +1,270 @@
-mod structs;
-mod course_service;
-mod tee_time_service;
+mod v1;
 
-use std::sync::Arc;
-use std::collections::HashMap;
+use tee_time_tracker_v2::{structs, course_service, tee_time_service, watch_service};
+
+use std::sync::{Arc, RwLock};
+use std::collections::{BTreeMap, HashMap};
 use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::convert::Infallible;
 
+use chrono::NaiveDate;
+use futures::stream::{self, Stream, StreamExt};
 use tower_http::cors::{CorsLayer, Any};
-use axum::http::Method;
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
 
 use axum::{
-    routing::get,
+    body::Body,
+    routing::{get, post, put, MethodRouter},
     Router,
-    response::Json,
+    middleware::{self, Next},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+        Json,
+        Response,
+    },
     extract::{
+        ws::WebSocketUpgrade,
+        Path,
+        Request,
         State,
         Query
     }
 };
 
 use course_service::courses::{
+    catalog_sources,
+    read_catalog,
     load_courses,
+    reload_courses,
+    watch_catalog,
+    build_catalog,
     get_courses,
+    courses_within,
+    courses_in_area,
+    nearest_courses,
+    world_coords,
+    default_coords,
+    matches_filter,
+    open_courses,
+    open_sheets,
+    cluster_courses,
+    Listing,
+};
+
+use course_service::geo::haversine_km;
+
+use course_service::search::{
+    tokenize,
+    name_score,
+};
+
+use course_service::admin::{
+    add_course,
+    update_course,
+    remove_course,
 };
 
-use tee_time_service::get_tee_times;
+use tee_time_service::csv as tee_time_csv;
+use tee_time_service::ics as tee_time_ics;
+use tee_time_service::atom as tee_time_atom;
+
+use watch_service::poller::{poll_tee_times, POLL_ENV, DEFAULT_POLL_SECS};
+use watch_service::feeds::{opened as opened_tee_times, FeedFilter};
+use watch_service::live::session as live_session;
+use tee_time_service::{
+    fetch_tee_sheets,
+    check_holes,
+    fits_request,
+    for_party,
+    search_updates,
+    stream_tee_sheets,
+    get_cached_tee_times,
+    summarize_by_course,
+    summarize_clusters,
+    known_tee_times,
+};
 
 use structs::{
     AppState,
+    ApiError,
+    Area,
+    Cluster,
+    Feature,
+    FeatureCollection,
+    OutputFormat,
+    CatalogSource,
+    CourseCatalog,
+    CourseDetail,
+    CourseFilter,
+    FetchLog,
+    TeeTimeCache,
+    TeeTimeHistory,
+    NewWatch,
+    TeeTime,
+    Watch,
+    Watches,
     GolfCourse,
     Coords,
-    TeeTime,
+    TeeTimeSearch,
+    CourseState,
+    CourseStatus,
+    NdJsonLine,
+    SearchUpdate,
+    BatchResult,
+    ClusterQuery,
+    CourseQuery,
+    CourseSelection,
+    CourseTeeTimeQuery,
+    FeedQuery,
+    TeeTimeQuery,
 };
 
 
+const DEFAULT_RADIUS_KM: f64 = 25.0;
+
+/// Longest `date`..`end_date` range a tee time search may cover.
+const MAX_SEARCH_DAYS: i64 = 14;
+
+/// Most searches one `POST /tee_times/batch` may hold.
+const MAX_BATCH_SEARCHES: usize = 20;
+
+/// Days a calendar feed covers when the search has no `end_date`.
+const CALENDAR_DAYS: i64 = 7;
+
+/// Deepest `/courses/clusters` zoom, matching common map tile servers.
+const MAX_ZOOM: u32 = 22;
+
+/// Shallowest `/courses/clusters` zoom that fetches tee sheets; below it, roughly a
+/// metro area or more, counts come from the cache and the watch poller only.
+const LIVE_CLUSTER_ZOOM: u32 = 10;
+
+const USAGE: &str = "Usage: tee_time_tracker_v2 [serve | validate-courses [PATH...]] [--catalog PATH]...";
+
 #[tokio::main]
 async fn main() {
-    let courses = load_courses();
+    let mut args = std::env::args().skip(1).peekable();
+
+    let command = match args.peek() {
+        Some(arg) if !arg.starts_with("--") => args.next(),
+        _ => None,
+    };
+
+    let mut catalog_paths: Vec<PathBuf> = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--catalog" => match args.next() {
+                Some(path) => catalog_paths.push(PathBuf::from(path)),
+                None => exit_with_usage("--catalog needs a path"),
+            },
+            _ if command.as_deref() == Some("validate-courses") && !arg.starts_with("--") => {
+                catalog_paths.push(PathBuf::from(arg));
+            }
+            _ => exit_with_usage(&format!("Unexpected argument {:?}", arg)),
+        }
+    }
+
+    let sources = match catalog_sources(&catalog_paths) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    match command.as_deref() {
+        None | Some("serve") => serve(catalog_paths, sources).await,
+        Some("validate-courses") => std::process::exit(validate_courses(&sources)),
+        Some(other) => exit_with_usage(&format!("Unknown command {:?}", other)),
+    }
+}
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    std::process::exit(2);
+}
+
+/// Checks course catalogs without starting the server, for CI on course-list changes.
+fn validate_courses(sources: &[CatalogSource]) -> i32 {
+    match read_catalog(sources) {
+        Ok(courses) => {
+            println!("{} courses OK across {} catalog(s)", courses.len(), sources.len());
+            0
+        }
+        Err(errors) => {
+            for error in &errors {
+                eprintln!("{}", error);
+            }
+            eprintln!("{} problem(s) found", errors.len());
+            1
+        }
+    }
+}
+
+async fn serve(catalog_paths: Vec<PathBuf>, sources: Vec<CatalogSource>) {
+    let courses = load_courses(&sources);
 
     let state = AppState {
-        courses: Arc::new(courses),
+        fetch_log: FetchLog::default(),
+        tee_time_cache: TeeTimeCache::default(),
+        watches: Watches::default(),
+        history: TeeTimeHistory::default(),
+        catalog_write: Arc::new(tokio::sync::Mutex::new(())),
+        courses: Arc::new(RwLock::new(Arc::new(build_catalog(courses)))),
+        catalog_paths: Arc::new(catalog_paths),
     };
 
+    // The built-in catalog can't change underneath us, so only watch real files
+    if sources != [CatalogSource::Builtin] {
+        let every = std::env::var("CATALOG_POLL_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(5);
+        tokio::spawn(watch_catalog(state.clone(), Duration::from_secs(every)));
+    }
+
+    let poll_secs = std::env::var(POLL_ENV)
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_POLL_SECS);
+    if poll_secs > 0 {
+        tokio::spawn(poll_tee_times(state.clone(), Duration::from_secs(poll_secs)));
+    } else {
+        println!("Tee time poller is off; set {} to fill watch feeds and /ws", POLL_ENV);
+    }
+
     let cors = CorsLayer::new()
         .allow_origin(Any) // allow all origins (safe for dev)
-        .allow_methods([Method::GET])
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
         .allow_headers(Any);
 
+    // The unversioned paths from before /v1, kept working for existing clients
+    let legacy = Router::new()
+        .route("/courses", get(courses_handler).post(courses_area_handler))
+        .route("/courses/clusters", get(clusters_handler))
+        .route("/courses/:slug", get(course_handler))
+        .route("/courses/:slug/tee_times", get(course_tee_times_handler))
+        .route("/courses/:slug/feed.atom", get(course_atom_handler))
+        .route("/tee_times", get(tee_times_handler).post(tee_times_area_handler))
+        .route("/tee_times/batch", post(batch_handler))
+        .route("/tee_times/stream", get(tee_times_stream_handler))
+        .route("/ws", get(ws_handler))
+        .route("/tee_times.ics", get(tee_times_ics_handler))
+        .route("/watches", watches_routes())
+        .route("/watches/:id", get(watch_handler).delete(delete_watch_handler))
+        .route("/watches/:id/feed.ics", get(watch_ics_handler))
+        .route("/watches/:id/feed.atom", get(watch_atom_handler))
+        .nest("/admin", admin_routes())
+        .layer(middleware::map_response(v1::json_errors))
+        .layer(middleware::from_fn(deprecated_alias));
+
     // build our application with a route
     let app = Router::new()
         .route("/", get(root))
-        .route("/courses", get(courses_handler))
-        .route("/tee_times", get(tee_times_handler))
+        .nest("/v1", v1::router())
+        .merge(legacy)
         .with_state(state)
         .layer(cors);
 
@@ -76,54 +289,1168 @@ async fn root() -> &'static str {
 }
 
 
+/// `/watches`: anyone can save a search, but only admins can list everyone's, since a
+/// watch id is all it takes to read its feeds.
+fn watches_routes() -> MethodRouter<AppState> {
+    get(list_watches_handler)
+        .route_layer(middleware::from_fn(require_admin))
+        .post(create_watch_handler)
+}
+
+
+/// Shared by `/v1/admin` and the unversioned `/admin`.
+fn admin_routes() -> Router<AppState> {
+    Router::new()
+        .route("/reload", post(reload_handler))
+        .route("/courses", post(add_course_handler))
+        .route("/courses/:slug", put(update_course_handler).delete(remove_course_handler))
+        .route_layer(middleware::from_fn(require_admin))
+}
+
+
+/// Marks responses on the unversioned paths as deprecated, pointing at their `/v1` successor.
+async fn deprecated_alias(request: Request, next: Next) -> Response {
+    let successor = format!("</v1{}>; rel=\"successor-version\"", request.uri().path());
+
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert("deprecation", HeaderValue::from_static("true"));
+    if let Ok(link) = HeaderValue::from_str(&successor) {
+        headers.insert(header::LINK, link);
+    }
+
+    response
+}
+
+
+/// Admin routes need `Authorization: Bearer $ADMIN_TOKEN`, and are off when it isn't set.
+async fn require_admin(request: Request, next: Next) -> Result<Response, ApiError> {
+    let token = std::env::var("ADMIN_TOKEN")
+        .ok()
+        .filter(|t| !t.is_empty())
+        .ok_or_else(|| ApiError::new(StatusCode::FORBIDDEN, "Admin API is disabled; set ADMIN_TOKEN to enable it"))?;
+
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    if provided != Some(token.as_str()) {
+        return Err(ApiError::new(StatusCode::UNAUTHORIZED, "Missing or invalid admin token"));
+    }
+
+    Ok(next.run(request).await)
+}
+
+
+#[utoipa::path(
+    post,
+    path = "/admin/reload",
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Catalog reloaded, with its course count", body = serde_json::Value),
+        (status = 401, description = "Missing or invalid admin token", body = ApiError),
+        (status = 403, description = "Admin API is disabled", body = ApiError),
+        (status = 422, description = "Catalog is invalid; the previous one stays loaded", body = ApiError),
+    ),
+)]
+async fn reload_handler(
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    match reload_courses(&state) {
+        Ok(count) => Ok(Json(serde_json::json!({
+            "status": "reloaded",
+            "courses": count,
+        }))),
+        Err(errors) => Err(ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Course catalog is invalid, keeping the previous catalog",
+        ).with_details(errors)),
+    }
+}
+
+
+#[utoipa::path(
+    post,
+    path = "/admin/courses",
+    tag = "admin",
+    security(("admin_token" = [])),
+    request_body = GolfCourse,
+    responses(
+        (status = 201, description = "Course added to the catalog", body = GolfCourse),
+        (status = 401, description = "Missing or invalid admin token", body = ApiError),
+        (status = 403, description = "Admin API is disabled", body = ApiError),
+        (status = 409, description = "Slug taken or catalog files invalid", body = ApiError),
+        (status = 422, description = "Invalid course", body = ApiError),
+    ),
+)]
+async fn add_course_handler(
+    State(state): State<AppState>,
+    Json(course): Json<GolfCourse>,
+) -> Result<(StatusCode, Json<GolfCourse>), ApiError> {
+    let course = add_course(&state, course).await?;
+
+    Ok((StatusCode::CREATED, Json(course)))
+}
+
+
+#[utoipa::path(
+    put,
+    path = "/admin/courses/{slug}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("slug" = String, Path)),
+    request_body = GolfCourse,
+    responses(
+        (status = 200, description = "Course replaced", body = GolfCourse),
+        (status = 401, description = "Missing or invalid admin token", body = ApiError),
+        (status = 403, description = "Admin API is disabled", body = ApiError),
+        (status = 404, description = "No such course", body = ApiError),
+        (status = 422, description = "Invalid course", body = ApiError),
+    ),
+)]
+async fn update_course_handler(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Json(course): Json<GolfCourse>,
+) -> Result<Json<GolfCourse>, ApiError> {
+    Ok(Json(update_course(&state, &slug, course).await?))
+}
+
+
+#[utoipa::path(
+    delete,
+    path = "/admin/courses/{slug}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("slug" = String, Path)),
+    responses(
+        (status = 200, description = "The removed course", body = GolfCourse),
+        (status = 401, description = "Missing or invalid admin token", body = ApiError),
+        (status = 403, description = "Admin API is disabled", body = ApiError),
+        (status = 404, description = "No such course", body = ApiError),
+    ),
+)]
+async fn remove_course_handler(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+) -> Result<Json<GolfCourse>, ApiError> {
+    Ok(Json(remove_course(&state, &slug).await?))
+}
+
+
 async fn courses_handler(
     State(state): State<AppState>,
-    Query(params): Query<HashMap<String, String>>,
-) -> Json<Vec<GolfCourse>> {
-    let coords = params
-        .get("coords")
-        .and_then(|value| serde_json::from_str::<Coords>(value).ok());
+    Query(query): Query<CourseQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    list_courses(&state, &query, &headers)
+}
+
+
+/// Same as `GET /courses`, with a GeoJSON area as the body instead of `area=`.
+async fn courses_area_handler(
+    State(state): State<AppState>,
+    Query(mut query): Query<CourseQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Response, ApiError> {
+    query.area = Some(body);
+
+    list_courses(&state, &query, &headers)
+}
+
+
+fn list_courses(
+    state: &AppState,
+    query: &CourseQuery,
+    headers: &HeaderMap,
+) -> Result<Response, ApiError> {
+    let format = output_format(query.format, headers);
+
+    let filter = CourseFilter {
+        holes: query.holes,
+        ..course_filter(query.selection(), None)
+    };
 
-    // get_courses returns Vec<&GolfCourse>, so we clone each course
-    let result: Vec<GolfCourse> = get_courses(&state.courses, coords)
+    // Disabled courses are left out, and with a date, courses closed that day too
+    let listing = query.date.map_or(Listing::Enabled, Listing::OpenOn);
+
+    let courses = state.courses();
+
+    // select_courses returns Vec<&GolfCourse>, so we clone each course
+    let result: Vec<GolfCourse> = select_courses(&courses, query.selection(), &filter, listing)?
         .into_iter()
         .cloned()
         .collect();
 
-    Json(result)
+    match format {
+        OutputFormat::Json => Ok(Json(result).into_response()),
+        OutputFormat::GeoJson => Ok(FeatureCollection::new(
+            result
+                .into_iter()
+                .map(|course| Feature::point(course.lat, course.lon, course))
+                .collect(),
+        ).into_response()),
+        OutputFormat::Csv | OutputFormat::NdJson => Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "csv and ndjson are only available for tee times",
+        )),
+    }
 }
 
 
 async fn tee_times_handler(
     State(state): State<AppState>,
-    Query(params): Query<HashMap<String, String>>,
-) -> Json<Vec<TeeTime>> {
-    let date: String = params
-        .get("date")
-        .cloned()
-        .unwrap_or_else(|| chrono::Local::now()
-            .format("%Y-%m-%d")
-            .to_string()
-        );
+    Query(query): Query<TeeTimeQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let courses = state.courses();
+    let filtered_courses = tee_time_courses(&courses, &query)?;
+
+    search_tee_times(&state, filtered_courses, &query, &headers).await
+}
+
+
+/// Same as `GET /tee_times`, with a GeoJSON area as the body instead of `area=`.
+async fn tee_times_area_handler(
+    State(state): State<AppState>,
+    Query(mut query): Query<TeeTimeQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Response, ApiError> {
+    query.area = Some(body);
+
+    tee_times_handler(State(state), Query(query), headers).await
+}
+
+
+/// Picks courses by `near` (with `radius_km` and/or `k`), `area` or `coords`, then
+/// applies the metadata filters and `q`. Best name matches come first; otherwise
+/// nearest first for `near`, catalog order for `coords`.
+fn select_courses<'a>(
+    catalog: &'a CourseCatalog,
+    selection: CourseSelection,
+    filter: &CourseFilter,
+    listing: Listing,
+) -> Result<Vec<&'a GolfCourse>, ApiError> {
+    let query = selection.q.map(tokenize).unwrap_or_default();
+
+    let located_by = [("near", selection.near), ("area", selection.area), ("coords", selection.coords)]
+        .into_iter()
+        .filter(|(_, value)| value.is_some())
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+    if located_by.len() > 1 {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("Use only one of near, area and coords, got {}", located_by.join(" and ")),
+        ));
+    }
+
+    let located: Vec<&GolfCourse> = match (selection.near, selection.area) {
+        (Some(near), _) => {
+            let center = parse_near(near)?;
+            let (k, radius_km) = (selection.k, selection.radius_km);
+            if radius_km.is_some_and(|r| r.is_nan() || r <= 0.0) {
+                return Err(ApiError::new(StatusCode::BAD_REQUEST, "radius_km must be greater than 0"));
+            }
+
+            let mut found = match (radius_km, k) {
+                (None, Some(k)) => nearest_courses(catalog, center, k, listing),
+                (radius_km, _) => courses_within(catalog, center, radius_km.unwrap_or(DEFAULT_RADIUS_KM), listing),
+            };
+            if let Some(k) = k {
+                found.truncate(k);
+            }
+
+            found.into_iter().map(|(course, _)| course).collect()
+        }
+        (None, Some(area)) => courses_in_area(catalog, &parse_area(area)?, listing),
+        (None, None) => {
+            let coords = selection.coords.map(parse_coords).transpose()?;
+
+            // A name search isn't limited to the default region
+            let coords = coords.or_else(|| (!query.is_empty()).then(world_coords));
+
+            get_courses(catalog, coords, listing)
+        }
+    };
+
+    let mut matched: Vec<(usize, &GolfCourse)> = located
+        .into_iter()
+        .filter(|course| matches_filter(course, filter))
+        .filter_map(|course| name_score(course, &query).map(|score| (score, course)))
+        .collect();
+
+    // Stable, so without `q` (every score 0) the located order is kept
+    matched.sort_by_key(|(score, _)| *score);
+
+    Ok(matched.into_iter().map(|(_, course)| course).collect())
+}
+
+
+/// `near=lat,lon`
+fn parse_near(value: &str) -> Result<(f64, f64), ApiError> {
+    let invalid = || ApiError::new(
+        StatusCode::BAD_REQUEST,
+        format!("near must be \"lat,lon\", got {:?}", value),
+    );
+
+    let (lat, lon) = value.split_once(',').ok_or_else(invalid)?;
+    let lat: f64 = lat.trim().parse().map_err(|_| invalid())?;
+    let lon: f64 = lon.trim().parse().map_err(|_| invalid())?;
+
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return Err(invalid());
+    }
+
+    Ok((lat, lon))
+}
+
+
+/// Where distances are measured from: the `near` point, else the middle of `area`
+/// or `coords`, else of the default region. A name search alone has no centre.
+fn search_center(selection: CourseSelection) -> Result<Option<(f64, f64)>, ApiError> {
+    if let Some(near) = selection.near {
+        return parse_near(near).map(Some);
+    }
+
+    let bounds = match (selection.area, selection.coords) {
+        (Some(area), _) => parse_area(area)?.bounds(),
+        (None, Some(coords)) => parse_coords(coords)?,
+        (None, None) if selection.q.is_some() => return Ok(None),
+        (None, None) => default_coords(),
+    };
+
+    Ok(Some(bounds.center()))
+}
 
-    let players = params
-        .get("players")
-        .and_then(|p| p.parse::<u32>().ok())
-        .unwrap_or(4);
 
-    // println!("RAW coords param: {:?}", params.get("coords"));
+/// `coords={"min_lat":..,"max_lat":..,"min_lon":..,"max_lon":..}`. A `min_lon`
+/// greater than `max_lon` is a box that wraps across the antimeridian.
+fn parse_coords(value: &str) -> Result<Coords, ApiError> {
+    let coords: Coords = serde_json::from_str(value).map_err(|e| ApiError::new(
+        StatusCode::BAD_REQUEST,
+        format!(
+            "coords must be JSON like {{\"min_lat\":38.7,\"max_lat\":39.4,\"min_lon\":-94.9,\"max_lon\":-94.2}}: {}",
+            e
+        ),
+    ))?;
 
-    let coords = params
-        .get("coords")
-        .and_then(|c| serde_json::from_str::<Coords>(c).ok());
+    coords
+        .validate()
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))?;
+
+    Ok(coords)
+}
+
+
+/// A GeoJSON Polygon or MultiPolygon, a Feature holding one, or a FeatureCollection of those.
+fn parse_area(value: &str) -> Result<Area, ApiError> {
+    let area: Area = serde_json::from_str(value).map_err(|e| ApiError::new(
+        StatusCode::BAD_REQUEST,
+        format!("area must be a GeoJSON Polygon, MultiPolygon, Feature or FeatureCollection: {}", e),
+    ))?;
+
+    area.validate()
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))?;
+
+    Ok(area)
+}
+
+
+#[utoipa::path(
+    get,
+    path = "/courses/{slug}",
+    tag = "courses",
+    params(("slug" = String, Path)),
+    responses(
+        (status = 200, description = "The course and its fetch status", body = CourseDetail),
+        (status = 404, description = "No such course", body = ApiError),
+    ),
+)]
+async fn course_handler(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+) -> Result<Json<CourseDetail>, ApiError> {
+    let courses = state.courses();
+    let course = find_course(&courses, &slug)?;
+
+    let mut detail = course.clone();
+    let hole_options = Some(std::mem::take(&mut detail.hole_options)).filter(|h| !h.is_empty());
+
+    Ok(Json(CourseDetail {
+        course: detail,
+        hole_options,
+        provider: course.provider.name(),
+        tee_times_supported: course.provider.is_supported(),
+        last_successful_fetch: state.fetch_log.last_success(&course.slug),
+    }))
+}
+
+
+async fn course_tee_times_handler(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Query(query): Query<CourseTeeTimeQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let courses = state.courses();
+    let course = find_course(&courses, &slug)?;
+
+    search_tee_times(&state, vec![course], &query.into(), &headers).await
+}
+
+
+/// The metadata filters, with `round_length` for tee time searches.
+fn course_filter(selection: CourseSelection, round_length: Option<u32>) -> CourseFilter {
+    CourseFilter {
+        walkable: selection.walkable,
+        driving_range: selection.driving_range,
+        access: selection.access,
+        holes: None,
+        round_length,
+    }
+}
+
+
+/// The courses a tee time search covers. `holes` is the round length here, so
+/// layout holes aren't filterable.
+fn tee_time_courses<'a>(catalog: &'a CourseCatalog, query: &TeeTimeQuery) -> Result<Vec<&'a GolfCourse>, ApiError> {
+    select_courses(catalog, query.selection(), &course_filter(query.selection(), query.holes), Listing::All)
+}
+
+
+fn find_course<'a>(catalog: &'a CourseCatalog, slug: &str) -> Result<&'a GolfCourse, ApiError> {
+    course_service::courses::find_course(catalog, slug)
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("No course with slug {:?}", slug)))
+}
+
+
+/// Shared by `/tee_times` and `/courses/{slug}/tee_times` once the courses are picked.
+async fn search_tee_times(
+    state: &AppState,
+    courses: Vec<&GolfCourse>,
+    query: &TeeTimeQuery,
+    headers: &HeaderMap,
+) -> Result<Response, ApiError> {
+    let format = output_format(query.format, headers);
+
+    match format {
+        OutputFormat::Csv => return csv_tee_times(state, courses, query),
+        OutputFormat::NdJson => return ndjson_tee_times(state, courses, query),
+        OutputFormat::Json | OutputFormat::GeoJson => {}
+    }
+    if query.end_date.is_some() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "end_date is only supported with csv or ndjson output and on /tee_times/stream",
+        ));
+    }
+
+    let (date, players, holes) = tee_time_request(query)?;
+
+    let searched = courses.clone();
+
+    // Closed courses are reported rather than fetched
+    let (courses, mut statuses) = open_courses(courses, date);
+
+    let mut sheets: HashMap<String, Result<Vec<TeeTime>, String>> = stream_tee_sheets(
+        state.fetch_log.clone(),
+        open_sheets(&courses, &[date]),
+        players,
+        holes,
+    )
+        .map(|sheet| (sheet.course.slug, sheet.result))
+        .collect()
+        .await;
+
+    // Statuses are in search order, so tee times stay grouped by course as before;
+    // a course whose tee sheet couldn't be fetched is reported, not shown as open
+    let mut tee_times = Vec::new();
+    for status in statuses.iter_mut().filter(|s| s.status == CourseState::Open) {
+        match sheets.remove(&status.slug) {
+            Some(Ok(found)) => tee_times.extend(found),
+            Some(Err(e)) => {
+                status.status = CourseState::Error;
+                status.reason = Some(e);
+            }
+            None => {}
+        }
+    }
+
+    if format == OutputFormat::GeoJson {
+        return Ok(summarize_by_course(&searched, &statuses, &tee_times).into_response());
+    }
+
+    if query.include_status == Some(true) {
+        Ok(Json(TeeTimeSearch {
+            tee_times,
+            courses: statuses,
+        }).into_response())
+    } else {
+        Ok(Json(tee_times).into_response())
+    }
+}
+
+
+/// `POST /tee_times/batch`: several `/tee_times` searches at once, keyed by `id` or
+/// position. A course, day and round length that more than one search needs is
+/// fetched once, for the smallest party, then re-totaled for each search.
+async fn batch_handler(
+    State(state): State<AppState>,
+    Json(specs): Json<Vec<serde_json::Value>>,
+) -> Result<Json<BTreeMap<String, BatchResult>>, ApiError> {
+    let searches = specs.into_iter().map(parse_batch_search).collect();
+
+    Ok(Json(batch_search(&state, searches).await?))
+}
+
+
+/// Reads one batch entry on its own, so a malformed search is reported under its key
+/// rather than failing the whole batch. `coords` and `area` may be JSON objects as
+/// well as JSON text. `id` is taken out first and the rest read as a `TeeTimeQuery`,
+/// so a misspelled parameter is an error here just as it is on `/tee_times`.
+fn parse_batch_search(mut spec: serde_json::Value) -> (Option<String>, Result<TeeTimeQuery, ApiError>) {
+    let invalid = |e: String| ApiError::new(StatusCode::BAD_REQUEST, format!("Invalid search: {}", e));
+
+    let mut id = None;
+    if let Some(fields) = spec.as_object_mut() {
+        for name in ["coords", "area"] {
+            if let Some(value) = fields.get_mut(name).filter(|v| v.is_object() || v.is_array()) {
+                *value = serde_json::Value::String(value.to_string());
+            }
+        }
+
+        match fields.remove("id") {
+            None | Some(serde_json::Value::Null) => {}
+            Some(serde_json::Value::String(key)) => id = Some(key),
+            Some(other) => return (None, Err(invalid(format!("id must be a string, got {}", other)))),
+        }
+    }
+
+    match serde_json::from_value::<TeeTimeQuery>(spec) {
+        Ok(query) => (id, Ok(query)),
+        Err(e) => (id, Err(invalid(e.to_string()))),
+    }
+}
+
+
+/// Runs a batch of `(id, query)` searches, each already parsed or failed.
+async fn batch_search(
+    state: &AppState,
+    searches: Vec<(Option<String>, Result<TeeTimeQuery, ApiError>)>,
+) -> Result<BTreeMap<String, BatchResult>, ApiError> {
+    if searches.is_empty() || searches.len() > MAX_BATCH_SEARCHES {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("A batch takes 1 to {} searches, got {}", MAX_BATCH_SEARCHES, searches.len()),
+        ));
+    }
+
+    let catalog = state.courses();
+
+    let mut plans: Vec<(String, Result<BatchPlan, ApiError>)> = Vec::new();
+    for (i, (id, search)) in searches.into_iter().enumerate() {
+        let key = id.unwrap_or_else(|| i.to_string());
+        if plans.iter().any(|(k, _)| *k == key) {
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, format!("Duplicate batch id {:?}", key)));
+        }
+
+        plans.push((key, search.and_then(|query| plan_batch_search(&catalog, &query))));
+    }
+
+    // One fetch per course, day and round length, for the smallest party that wants it
+    let mut shared: HashMap<SheetKey, (GolfCourse, u32)> = HashMap::new();
+    for (_, plan) in &plans {
+        let Ok(plan) = plan else { continue };
+        for (course, date) in &plan.sheets {
+            shared
+                .entry((course.slug.clone(), *date, plan.holes))
+                .and_modify(|(_, players)| *players = (*players).min(plan.players))
+                .or_insert(((*course).clone(), plan.players));
+        }
+    }
+
+    let requests = shared
+        .into_iter()
+        .map(|((_, date, holes), (course, players))| (course, date, players, holes))
+        .collect();
+    let fetched: HashMap<SheetKey, Result<Vec<TeeTime>, String>> = fetch_tee_sheets(state.fetch_log.clone(), requests)
+        .map(|sheet| ((sheet.course.slug, sheet.date, sheet.holes), sheet.result))
+        .collect()
+        .await;
+
+    let results = plans
+        .into_iter()
+        .map(|(key, plan)| {
+            let plan = match plan {
+                Ok(plan) => plan,
+                Err(e) => return (key, BatchResult::Error(e)),
+            };
+
+            let mut tee_times = Vec::new();
+            let mut statuses = plan.closed;
+            for (course, date) in plan.sheets {
+                let (status, reason) = match fetched.get(&(course.slug.clone(), date, plan.holes)) {
+                    Some(Ok(sheet)) => {
+                        tee_times.extend(
+                            sheet
+                                .iter()
+                                .filter(|tt| fits_request(tt, plan.players, plan.holes))
+                                .map(|tt| for_party(tt, plan.players)),
+                        );
+                        (CourseState::Open, None)
+                    }
+                    Some(Err(e)) => (CourseState::Error, Some(e.clone())),
+                    None => {
+                        eprintln!("Batch search {:?} has no tee sheet for {} on {}", key, course.slug, date);
+                        (CourseState::Error, Some("tee sheet was not fetched".to_string()))
+                    }
+                };
+
+                statuses.push(CourseStatus {
+                    slug: course.slug.clone(),
+                    course: course.name.clone(),
+                    date,
+                    status,
+                    reason,
+                });
+            }
+            statuses.sort_by(|a, b| (a.date, &a.course).cmp(&(b.date, &b.course)));
+
+            (key, BatchResult::Search(TeeTimeSearch { tee_times, courses: statuses }))
+        })
+        .collect();
+
+    Ok(results)
+}
+
+
+/// A tee sheet shared between batch searches: course slug, day and round length.
+type SheetKey = (String, NaiveDate, Option<u32>);
+
+/// What one batch search needs fetched, and what it reports without fetching.
+struct BatchPlan<'a> {
+    sheets: Vec<(&'a GolfCourse, NaiveDate)>,
+    closed: Vec<CourseStatus>,
+    players: u32,
+    holes: Option<u32>,
+}
+
+fn plan_batch_search<'a>(
+    catalog: &'a CourseCatalog,
+    query: &TeeTimeQuery,
+) -> Result<BatchPlan<'a>, ApiError> {
+    let courses = tee_time_courses(catalog, query)?;
+    let (_, players, holes) = tee_time_request(query)?;
+    let dates = search_dates(query, 1)?;
+
+    let mut sheets = Vec::new();
+    let mut closed = Vec::new();
+    for &date in &dates {
+        let (open, statuses) = open_courses(courses.clone(), date);
+        sheets.extend(open.into_iter().map(|course| (course, date)));
+        closed.extend(statuses.into_iter().filter(|s| s.status == CourseState::Closed));
+    }
+
+    Ok(BatchPlan { sheets, closed, players, holes })
+}
+
+
+/// `/tee_times/stream`: the same search as `/tee_times` as Server-Sent Events. Each
+/// course's tee times arrive in a `course` event as soon as its provider answers,
+/// then a `summary` event lists every course's status. Takes `end_date` too.
+async fn tee_times_stream_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TeeTimeQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let catalog = state.courses();
+    let courses = tee_time_courses(&catalog, &query)?;
+
+    let updates = stream_search(&state, courses, &query)?;
+    let events = updates.map(|update| {
+        let event = match update {
+            SearchUpdate::Course(result) => Event::default().event("course").json_data(result),
+            SearchUpdate::Done(summary) => Event::default().event("summary").json_data(summary),
+        };
+
+        Ok(event.expect("search updates serialize to JSON"))
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+
+/// Starts fetching every open course for each day of the search, closed ones
+/// going straight to the summary.
+fn stream_search(
+    state: &AppState,
+    courses: Vec<&GolfCourse>,
+    query: &TeeTimeQuery,
+) -> Result<impl Stream<Item = SearchUpdate> + use<>, ApiError> {
+    let (_, players, holes) = tee_time_request(query)?;
+    let dates = search_dates(query, 1)?;
+
+    let closed: Vec<CourseStatus> = dates
+        .iter()
+        .flat_map(|&date| open_courses(courses.clone(), date).1)
+        .filter(|status| status.status == CourseState::Closed)
+        .collect();
+
+    Ok(search_updates(
+        state.fetch_log.clone(),
+        open_sheets(&courses, &dates),
+        closed,
+        players,
+        holes,
+    ))
+}
+
+
+/// Streams tee times as newline-delimited JSON, a course's lines at a time as each
+/// tee sheet arrives, so nothing is held back for the slowest provider. Each line is
+/// an `NdJsonLine` tagged by `type`: a `tee_time`, or, for a tee sheet that couldn't be
+/// fetched, a `course_status` with `"status":"error"`, so a client can tell a failed
+/// course from one with no tee times. Takes `end_date`.
+fn ndjson_tee_times(
+    state: &AppState,
+    courses: Vec<&GolfCourse>,
+    query: &TeeTimeQuery,
+) -> Result<Response, ApiError> {
+    let (_, players, holes) = tee_time_request(query)?;
+    let dates = search_dates(query, 1)?;
+
+    let lines = stream_tee_sheets(state.fetch_log.clone(), open_sheets(&courses, &dates), players, holes)
+        .map(|sheet| {
+            let lines: String = match sheet.result {
+                Ok(tee_times) => tee_times
+                    .into_iter()
+                    .map(|tt| {
+                        serde_json::to_string(&NdJsonLine::TeeTime(tt)).expect("tee times serialize to JSON") + "\n"
+                    })
+                    .collect(),
+                Err(e) => {
+                    eprintln!("NDJSON export failed {} on {}: {}", sheet.course.name, sheet.date, e);
+
+                    let status = CourseStatus {
+                        slug: sheet.course.slug.clone(),
+                        course: sheet.course.name.clone(),
+                        date: sheet.date,
+                        status: CourseState::Error,
+                        reason: Some(e),
+                    };
+                    serde_json::to_string(&NdJsonLine::CourseStatus(status))
+                        .expect("course statuses serialize to JSON") + "\n"
+                }
+            };
+
+            Ok::<_, Infallible>(lines)
+        });
+
+    Ok((
+        [(header::CONTENT_TYPE, OutputFormat::NDJSON_TYPE)],
+        Body::from_stream(lines),
+    ).into_response())
+}
+
+
+/// Streams tee times as CSV rows while each course's tee sheet arrives. Takes
+/// `end_date` for multi-day searches, up to `MAX_SEARCH_DAYS` days.
+fn csv_tee_times(
+    state: &AppState,
+    courses: Vec<&GolfCourse>,
+    query: &TeeTimeQuery,
+) -> Result<Response, ApiError> {
+    let (_, players, holes) = tee_time_request(query)?;
+    let dates = search_dates(query, 1)?;
+
+    let center = search_center(query.selection())?;
+
+    // Closed courses are skipped; CSV has nowhere to report them
+    let sheets = open_sheets(&courses, &dates);
+
+    let rows = stream_tee_sheets(state.fetch_log.clone(), sheets, players, holes).map(move |sheet| {
+        // CSV has no column for failures, so they only reach the log
+        let tee_times = sheet.result.unwrap_or_else(|e| {
+            eprintln!("CSV export skipped {} on {}: {}", sheet.course.name, sheet.date, e);
+            Vec::new()
+        });
+
+        let rows: String = tee_times
+            .iter()
+            .map(|tt| tee_time_csv::row(tt, center.map(|(lat, lon)| haversine_km(lat, lon, tt.lat, tt.lon))))
+            .collect();
+
+        Ok::<_, Infallible>(rows)
+    });
+    let body = stream::once(async { Ok(tee_time_csv::HEADER.to_string()) }).chain(rows);
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"tee_times.csv\""),
+        ],
+        Body::from_stream(body),
+    ).into_response())
+}
+
+
+/// `date` through `end_date` (inclusive), both `YYYY-MM-DD`. Without `end_date`
+/// the search covers `default_days` days.
+fn search_dates(query: &TeeTimeQuery, default_days: i64) -> Result<Vec<NaiveDate>, ApiError> {
+    let (start, _, _) = tee_time_request(query)?;
+    let end = query
+        .end_date
+        .unwrap_or_else(|| start + chrono::Duration::days(default_days - 1));
+
+    let days = (end - start).num_days() + 1;
+    if !(1..=MAX_SEARCH_DAYS).contains(&days) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("end_date must be between date and {} days after it", MAX_SEARCH_DAYS - 1),
+        ));
+    }
+
+    Ok(start.iter_days().take(days as usize).collect())
+}
+
+
+/// `/tee_times.ics`: the same search as `/tee_times`, as a calendar to subscribe to.
+async fn tee_times_ics_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TeeTimeQuery>,
+) -> Result<Response, ApiError> {
+    let catalog = state.courses();
+    let courses = tee_time_courses(&catalog, &query)?;
+
+    calendar_feed(&state, "Tee times", courses, &query).await
+}
+
+
+/// Tee times from `date` (default today) through `end_date` (default a week) as
+/// iCalendar events, soonest first.
+async fn calendar_feed(
+    state: &AppState,
+    name: &str,
+    courses: Vec<&GolfCourse>,
+    query: &TeeTimeQuery,
+) -> Result<Response, ApiError> {
+    let (_, players, holes) = tee_time_request(query)?;
+    let dates = search_dates(query, CALENDAR_DAYS)?;
+
+    let mut tee_times: Vec<TeeTime> = stream_tee_sheets(
+        state.fetch_log.clone(),
+        open_sheets(&courses, &dates),
+        players,
+        holes,
+    )
+    .flat_map(|sheet| stream::iter(sheet.result.unwrap_or_default()))
+    .collect()
+    .await;
+    tee_times.sort_by_key(|tt| tt.tee_time);
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+        tee_time_ics::calendar(name, &tee_times),
+    ).into_response())
+}
+
+
+/// `POST /watches`: saves a `/tee_times` search. The query is checked now so a
+/// typo shows up here rather than as an empty feed later.
+#[utoipa::path(
+    post,
+    path = "/watches",
+    tag = "watches",
+    request_body = NewWatch,
+    responses(
+        (status = 201, description = "Saved search", body = Watch),
+        (status = 400, description = "Invalid search", body = ApiError),
+    ),
+)]
+async fn create_watch_handler(
+    State(state): State<AppState>,
+    Json(new_watch): Json<NewWatch>,
+) -> Result<(StatusCode, Json<Watch>), ApiError> {
+    let catalog = state.courses();
+    tee_time_courses(&catalog, &new_watch.query)?;
+    tee_time_search_check(&new_watch.query)?;
+
+    let watch = Watch {
+        id: new_watch_id()?,
+        name: if new_watch.name.trim().is_empty() { "Tee times".to_string() } else { new_watch.name },
+        query: new_watch.query,
+        created: chrono::Utc::now(),
+    };
+    state.watches.insert(watch.clone());
+
+    Ok((StatusCode::CREATED, Json(watch)))
+}
+
+
+#[utoipa::path(
+    get,
+    path = "/watches",
+    tag = "watches",
+    responses(
+        (status = 200, description = "Saved searches, oldest first", body = [Watch]),
+        (status = 401, description = "Missing or invalid admin token", body = ApiError),
+        (status = 403, description = "ADMIN_TOKEN isn't set", body = ApiError),
+    ),
+    security(("admin_token" = [])),
+)]
+async fn list_watches_handler(State(state): State<AppState>) -> Json<Vec<Watch>> {
+    Json(state.watches.list())
+}
+
+
+#[utoipa::path(
+    get,
+    path = "/watches/{id}",
+    tag = "watches",
+    params(("id" = String, Path)),
+    responses(
+        (status = 200, description = "Saved search", body = Watch),
+        (status = 404, description = "No such watch", body = ApiError),
+    ),
+)]
+async fn watch_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Watch>, ApiError> {
+    Ok(Json(find_watch(&state, &id)?))
+}
+
+
+#[utoipa::path(
+    delete,
+    path = "/watches/{id}",
+    tag = "watches",
+    params(("id" = String, Path)),
+    responses(
+        (status = 200, description = "The deleted search", body = Watch),
+        (status = 404, description = "No such watch", body = ApiError),
+    ),
+)]
+async fn delete_watch_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Watch>, ApiError> {
+    state.watches
+        .remove(&id)
+        .map(Json)
+        .ok_or_else(|| watch_not_found(&id))
+}
+
+
+#[utoipa::path(
+    get,
+    path = "/watches/{id}/feed.ics",
+    tag = "watches",
+    params(("id" = String, Path)),
+    responses(
+        (status = 200, description = "The search's tee times as iCalendar events", body = String, content_type = "text/calendar"),
+        (status = 404, description = "No such watch", body = ApiError),
+    ),
+)]
+async fn watch_ics_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, ApiError> {
+    let watch = find_watch(&state, &id)?;
+    let catalog = state.courses();
+    let courses = tee_time_courses(&catalog, &watch.query)?;
+
+    calendar_feed(&state, &watch.name, courses, &watch.query).await
+}
+
+
+/// Tee times that opened up since earlier polls and match the watch's search.
+/// Stays empty unless `WATCH_POLL_SECS` turns the poller on.
+#[utoipa::path(
+    get,
+    path = "/watches/{id}/feed.atom",
+    tag = "watches",
+    params(("id" = String, Path)),
+    responses(
+        (status = 200, description = "Newly opened tee times matching the search", body = String, content_type = "application/atom+xml"),
+        (status = 404, description = "No such watch", body = ApiError),
+    ),
+)]
+async fn watch_atom_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, ApiError> {
+    let watch = find_watch(&state, &id)?;
+    let catalog = state.courses();
+    let courses = tee_time_courses(&catalog, &watch.query)?;
+
+    let (_, players, holes) = tee_time_request(&watch.query)?;
+    let dates = if watch.query.date.is_some() {
+        Some(search_dates(&watch.query, 1)?)
+    } else {
+        None
+    };
+
+    let filter = FeedFilter {
+        slugs: courses.iter().map(|course| course.slug.clone()).collect(),
+        players,
+        holes,
+        dates,
+    };
+    let entries = opened_tee_times(state.history.changes(), &filter);
+
+    Ok(atom_response(tee_time_atom::feed(&format!("watch/{}", watch.id), &watch.name, &entries)))
+}
+
+
+/// Tee times that opened up at one course since earlier polls. Takes `players` and `holes`.
+/// Stays empty unless `WATCH_POLL_SECS` turns the poller on.
+async fn course_atom_handler(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Query(query): Query<FeedQuery>,
+) -> Result<Response, ApiError> {
+    let catalog = state.courses();
+    let course = find_course(&catalog, &slug)?;
+
+    let filter = FeedFilter {
+        slugs: [course.slug.clone()].into(),
+        players: query.players.unwrap_or(1),
+        holes: check_holes(query.holes)
+            .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))?,
+        dates: None,
+    };
+    let entries = opened_tee_times(state.history.changes(), &filter);
+
+    Ok(atom_response(tee_time_atom::feed(
+        &format!("course/{}", course.slug),
+        &format!("New tee times at {}", course.name),
+        &entries,
+    )))
+}
+
+
+/// `/ws`: live tee sheet changes for a subscription, as the poller sees them, so
+/// nothing arrives unless `WATCH_POLL_SECS` turns the poller on.
+#[utoipa::path(
+    get,
+    path = "/ws",
+    tag = "tee_times",
+    responses((
+        status = 101,
+        description = "Switches to a WebSocket. The client sends `ClientMessage`s and the server answers with `ServerMessage`s.",
+    )),
+)]
+async fn ws_handler(State(state): State<AppState>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| live_session(socket, state))
+}
+
+
+fn atom_response(xml: String) -> Response {
+    ([(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")], xml).into_response()
+}
+
+
+fn find_watch(state: &AppState, id: &str) -> Result<Watch, ApiError> {
+    state.watches.get(id).ok_or_else(|| watch_not_found(id))
+}
+
+
+fn watch_not_found(id: &str) -> ApiError {
+    ApiError::new(StatusCode::NOT_FOUND, format!("No watch with id {:?}", id))
+}
+
+
+/// Catches date problems in a saved query that course selection doesn't look at.
+fn tee_time_search_check(query: &TeeTimeQuery) -> Result<(), ApiError> {
+    if query.date.is_some() {
+        search_dates(query, CALENDAR_DAYS)?;
+    }
+    tee_time_request(query)?;
+
+    Ok(())
+}
+
+
+/// 128 bits from the OS random number generator, since anyone with the id can read the feed.
+fn new_watch_id() -> Result<String, ApiError> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|e| ApiError::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("Couldn't generate a watch id: {}", e),
+    ))?;
+
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+
+/// `date` (default today), `players` (default 4) and `holes` for a tee time search.
+fn tee_time_request(query: &TeeTimeQuery) -> Result<(NaiveDate, u32, Option<u32>), ApiError> {
+    let date = query.date.unwrap_or_else(|| chrono::Local::now().date_naive());
+
+    let players = query.players.unwrap_or(4);
+
+    check_holes(query.holes).map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))?;
+
+    Ok((date, players, query.holes))
+}
+
+
+/// Courses grouped for a zoomed-out map, with each cluster's tee time count and
+/// cheapest price. Takes the same course and tee time parameters as `/tee_times`.
+async fn clusters_handler(
+    State(state): State<AppState>,
+    Query(query): Query<ClusterQuery>,
+) -> Result<FeatureCollection<Cluster>, ApiError> {
+    let zoom = query.zoom;
+    if zoom > MAX_ZOOM {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("zoom must be a whole number from 0 to {}", MAX_ZOOM),
+        ));
+    }
+
+    let filter = course_filter(query.selection(), query.holes);
+    let catalog = state.courses();
+    let courses = select_courses(&catalog, query.selection(), &filter, Listing::All)?;
+
+    let (date, players, holes) = tee_time_request(&TeeTimeQuery {
+        date: query.date,
+        players: query.players,
+        holes: query.holes,
+        ..TeeTimeQuery::default()
+    })?;
+    let open = open_courses(courses.clone(), date).0;
+    let date = date.format("%Y-%m-%d").to_string();
+
+    // Zoomed out, a viewport can hold hundreds of courses, so counts come only from tee
+    // sheets already on hand. Zoomed in, panning asks again and again, so sheets still
+    // come from the cache when fresh.
+    let tee_times = if zoom < LIVE_CLUSTER_ZOOM {
+        known_tee_times(&state.tee_time_cache, &state.history, &open, &date, players, holes)
+    } else {
+        get_cached_tee_times(
+            &state.fetch_log,
+            &state.tee_time_cache,
+            &open,
+            &date,
+            players,
+            holes,
+        ).await
+    };
+
+    Ok(summarize_clusters(&cluster_courses(courses, zoom), &tee_times))
+}
 
-    let filtered_courses= get_courses(&state.courses, coords);
 
-    let tee_times = get_tee_times(
-        &filtered_courses, 
-        &date, 
-        players, 
-    ).await;
+/// `format=json|geojson|csv|ndjson`, falling back to the `Accept` header.
+fn output_format(format: Option<OutputFormat>, headers: &HeaderMap) -> OutputFormat {
+    let accept = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok());
 
-    Json(tee_times)
+    OutputFormat::negotiate(format, accept)
 }
@@ -1,32 +1,459 @@
 use serde::{Deserialize, Serialize, Deserializer};
-use std::sync::Arc;
-use chrono::{DateTime, Utc};
+use utoipa::{IntoParams, ToSchema};
+use std::sync::{Arc, RwLock};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use chrono_tz::Tz;
+use rstar::{RTree, primitives::GeomWithData};
+use tokio::sync::broadcast;
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
-pub struct VerboseCourseId {
+/// BookATeeTime search id, `"{facility}-{course}"` (e.g. `"118-1"`).
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(transparent)]
+pub struct BookATeeTimeId(pub String);
+
+/// GolfBack course UUID.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(transparent)]
+pub struct GolfBackId(pub String);
+
+/// ForeUp tee sheet. `facility_id` and `booking_class` fall back to the ones every
+/// ForeUp course used before they were configurable; they're only right for courses
+/// on that facility, so a course elsewhere needs its own.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ForeUpCourseId {
+    pub schedule_id: i64,
+    #[serde(default = "ForeUpCourseId::legacy_facility_id")]
+    pub facility_id: i64,
+    #[serde(default = "ForeUpCourseId::legacy_booking_class")]
+    pub booking_class: i64,
+}
+
+impl ForeUpCourseId {
+    fn legacy_facility_id() -> i64 {
+        22857
+    }
+
+    fn legacy_booking_class() -> i64 {
+        14824
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TeeItUpCourseId {
     pub id: i64,
     pub url: String,
     pub alias: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-#[serde(untagged)]
-pub enum CourseId {
-    Number(i64),
-    String(String),
-    Verbose(VerboseCourseId)
+/// Catalogued but not fetched yet.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(transparent)]
+pub struct ChronoGolfId(pub i64);
+
+/// Catalogued but not fetched yet; the id is the course's own tee sheet URL.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(transparent)]
+pub struct LonerId(pub String);
+
+/// Catalogued but not fetched yet.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(transparent)]
+pub struct CpsId(pub String);
+
+/// Booking platform for a course, tagged by `source` with its settings under `id`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, ToSchema)]
+#[serde(tag = "source", content = "id", rename_all = "lowercase")]
+pub enum Provider {
+    BookATeeTime(BookATeeTimeId),
+    GolfBack(GolfBackId),
+    ForeUp(ForeUpCourseId),
+    TeeItUp(TeeItUpCourseId),
+    ChronoGolf(ChronoGolfId),
+    Loner(LonerId),
+    Cps(CpsId),
+}
+
+impl Provider {
+    pub const SOURCES: [&'static str; 7] = [
+        "bookateetime",
+        "golfback",
+        "foreup",
+        "teeitup",
+        "chronogolf",
+        "loner",
+        "cps",
+    ];
+
+    /// Identity of the course on its platform, used to spot duplicates and overrides.
+    pub fn key(&self) -> String {
+        match self {
+            Provider::BookATeeTime(BookATeeTimeId(id)) => format!("bookateetime:{}", id),
+            Provider::GolfBack(GolfBackId(id)) => format!("golfback:{}", id.to_lowercase()),
+            Provider::ForeUp(f) => format!("foreup:{}/{}", f.facility_id, f.schedule_id),
+            Provider::TeeItUp(t) => format!("teeitup:{}", t.id),
+            Provider::ChronoGolf(ChronoGolfId(id)) => format!("chronogolf:{}", id),
+            Provider::Loner(LonerId(url)) => format!("loner:{}", url),
+            Provider::Cps(CpsId(id)) => format!("cps:{}", id),
+        }
+    }
+
+    /// Whether tee sheets can be fetched for this platform yet.
+    pub fn is_supported(&self) -> bool {
+        matches!(
+            self,
+            Provider::BookATeeTime(_) | Provider::GolfBack(_) | Provider::ForeUp(_) | Provider::TeeItUp(_)
+        )
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Provider::BookATeeTime(_) => "BookATeeTime",
+            Provider::GolfBack(_) => "GolfBack",
+            Provider::ForeUp(_) => "ForeUp",
+            Provider::TeeItUp(_) => "TeeItUp",
+            Provider::ChronoGolf(_) => "Chronogolf",
+            Provider::Loner(_) => "Course website",
+            Provider::Cps(_) => "CPS Golf",
+        }
+    }
+
+    pub fn source(&self) -> &'static str {
+        match self {
+            Provider::BookATeeTime(_) => "bookateetime",
+            Provider::GolfBack(_) => "golfback",
+            Provider::ForeUp(_) => "foreup",
+            Provider::TeeItUp(_) => "teeitup",
+            Provider::ChronoGolf(_) => "chronogolf",
+            Provider::Loner(_) => "loner",
+            Provider::Cps(_) => "cps",
+        }
+    }
+
+    /// Checks the id beyond its JSON shape, e.g. that a GolfBack id is a UUID.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Provider::BookATeeTime(BookATeeTimeId(id)) => {
+                let valid = id
+                    .split_once('-')
+                    .is_some_and(|(facility, course)| is_digits(facility) && is_digits(course));
+                if !valid {
+                    return Err(format!("bookateetime id {:?} must look like \"118-1\"", id));
+                }
+            }
+            Provider::GolfBack(GolfBackId(id)) => {
+                if !is_uuid(id) {
+                    return Err(format!("golfback id {:?} must be a UUID", id));
+                }
+            }
+            Provider::ForeUp(f) => {
+                if f.schedule_id <= 0 || f.facility_id <= 0 || f.booking_class <= 0 {
+                    return Err("foreup schedule_id, facility_id and booking_class must be positive".to_string());
+                }
+            }
+            Provider::TeeItUp(t) => {
+                if t.id <= 0 {
+                    return Err("teeitup id must be positive".to_string());
+                }
+                if !t.url.starts_with("https://") {
+                    return Err(format!("teeitup url {:?} must start with https://", t.url));
+                }
+                if t.alias.trim().is_empty() {
+                    return Err("teeitup alias must not be empty".to_string());
+                }
+            }
+            Provider::ChronoGolf(ChronoGolfId(id)) => {
+                if *id <= 0 {
+                    return Err("chronogolf id must be positive".to_string());
+                }
+            }
+            Provider::Loner(LonerId(url)) => {
+                if !url.starts_with("http") {
+                    return Err(format!("loner id {:?} must be the course's tee sheet URL", url));
+                }
+            }
+            Provider::Cps(CpsId(id)) => {
+                if id.trim().is_empty() {
+                    return Err("cps id must not be empty".to_string());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn is_digits(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_digit())
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+fn is_uuid(s: &str) -> bool {
+    let groups: Vec<&str> = s.split('-').collect();
+    let lengths = [8, 4, 4, 4, 12];
+
+    groups.len() == lengths.len()
+        && groups
+            .iter()
+            .zip(lengths)
+            .all(|(g, len)| g.len() == len && g.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
 pub struct GolfCourse {
-    pub id: CourseId,
+    /// URL-safe identifier, derived from `name` when the catalog leaves it out.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub slug: String,
     pub name: String,
+    /// Other names people search for, e.g. "Dubs".
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
     pub lat: f64,
     pub lon: f64,
-    pub source: String,
+    /// IANA time zone of the course's tee sheet, America/Chicago when the catalog leaves it out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, example = "America/Denver")]
+    pub timezone: Option<Tz>,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    /// Round lengths the course can be booked for, empty when the catalog doesn't say.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hole_options: Vec<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub season: Option<Season>,
+    /// One-off closures, e.g. aeration or tournament days.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub closures: Vec<NaiveDate>,
+    #[serde(flatten)]
+    pub metadata: CourseMetadata,
+    #[serde(flatten)]
+    pub provider: Provider,
 }
 
-#[derive(Debug, Deserialize)]
+impl GolfCourse {
+    pub const DEFAULT_TIMEZONE: Tz = chrono_tz::America::Chicago;
+
+    pub fn timezone(&self) -> Tz {
+        self.timezone.unwrap_or(Self::DEFAULT_TIMEZONE)
+    }
+}
+
+/// Optional details for choosing where to play. Anything missing is unknown.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, ToSchema)]
+pub struct CourseMetadata {
+    /// Holes on the layout, as opposed to the round lengths in `hole_options`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub holes: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub par: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tees: Vec<Tee>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub walking_allowed: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub driving_range: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access: Option<CourseAccess>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub website: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+}
+
+impl CourseMetadata {
+    /// Range checks, as `(field, message)` pairs.
+    pub fn validate(&self) -> Vec<(String, String)> {
+        let mut problems = Vec::new();
+
+        if self.holes.is_some_and(|h| !(1..=36).contains(&h)) {
+            problems.push(("holes".to_string(), "holes must be between 1 and 36".to_string()));
+        }
+        if self.par.is_some_and(|p| !(27..=80).contains(&p)) {
+            problems.push(("par".to_string(), "par must be between 27 and 80".to_string()));
+        }
+        if self.website.as_deref().is_some_and(|w| !w.starts_with("http")) {
+            problems.push(("website".to_string(), "website must be an http(s) URL".to_string()));
+        }
+
+        for (i, tee) in self.tees.iter().enumerate() {
+            if tee.slope.is_some_and(|s| !(55..=155).contains(&s)) {
+                problems.push((format!("tees[{}].slope", i), "slope must be between 55 and 155".to_string()));
+            }
+            if tee.rating.is_some_and(|r| !(20.0..=80.0).contains(&r)) {
+                problems.push((format!("tees[{}].rating", i), "rating must be between 20 and 80".to_string()));
+            }
+        }
+
+        problems
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, ToSchema)]
+pub struct Tee {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub yardage: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slope: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CourseAccess {
+    Public,
+    SemiPrivate,
+    Private,
+}
+
+/// Metadata filters for `/courses` and `/tee_times`. A course whose metadata
+/// doesn't say is left out of a filter that asks for it.
+#[derive(Debug, Default)]
+pub struct CourseFilter {
+    pub walkable: Option<bool>,
+    pub driving_range: Option<bool>,
+    pub access: Option<CourseAccess>,
+    pub holes: Option<u32>,
+    /// Round length a tee time search asks for. Courses that don't list their
+    /// `hole_options` are kept, since they may still offer it.
+    pub round_length: Option<u32>,
+}
+
+/// Days of the year a course is open, both inclusive. A season whose `close` falls
+/// before its `open` wraps over the new year.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, ToSchema)]
+pub struct Season {
+    #[schema(value_type = String, example = "04-01")]
+    pub open: MonthDay,
+    #[schema(value_type = String, example = "10-31")]
+    pub close: MonthDay,
+}
+
+impl Season {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        let day = MonthDay {
+            month: date.month(),
+            day: date.day(),
+        };
+
+        if self.open <= self.close {
+            self.open <= day && day <= self.close
+        } else {
+            day >= self.open || day <= self.close
+        }
+    }
+}
+
+/// A calendar day without a year, written `"MM-DD"`.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(try_from = "String", into = "String")]
+pub struct MonthDay {
+    pub month: u32,
+    pub day: u32,
+}
+
+impl TryFrom<String> for MonthDay {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("{:?} is not a valid MM-DD day", value);
+        let (month, day) = value.split_once('-').ok_or_else(invalid)?;
+        let month: u32 = month.parse().map_err(|_| invalid())?;
+        let day: u32 = day.parse().map_err(|_| invalid())?;
+
+        // 2024 is a leap year, so 02-29 is accepted
+        NaiveDate::from_ymd_opt(2024, month, day).ok_or_else(invalid)?;
+
+        Ok(MonthDay { month, day })
+    }
+}
+
+impl From<MonthDay> for String {
+    fn from(value: MonthDay) -> Self {
+        format!("{:02}-{:02}", value.month, value.day)
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CourseState {
+    Open,
+    Closed,
+    /// Searched, but the tee sheet couldn't be fetched.
+    Error,
+}
+
+/// Whether a course was searched for a date, and why not if it wasn't.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct CourseStatus {
+    pub slug: String,
+    pub course: String,
+    pub date: NaiveDate,
+    pub status: CourseState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// Lowercases `name` and joins its words with dashes: "Dub's Dread" becomes "dubs-dread".
+pub fn slugify(name: &str) -> String {
+    name.to_lowercase()
+        .replace(['\'', '\u{2019}'], "")
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Where a set of courses was loaded from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CatalogSource {
+    /// The catalog compiled into the binary, used when no catalog is configured.
+    Builtin,
+    File(std::path::PathBuf),
+}
+
+impl std::fmt::Display for CatalogSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CatalogSource::Builtin => write!(f, "built-in golf_courses.json"),
+            CatalogSource::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// A problem found while validating the course catalog, located by JSON path.
+#[derive(Debug, Serialize, Clone)]
+pub struct CourseIssue {
+    pub path: String,
+    pub course: Option<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for CourseIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.course {
+            Some(name) => write!(f, "{} ({}): {}", self.path, name, self.message),
+            None => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct Coords {
     pub min_lat: f64,
     pub max_lat: f64,
@@ -34,23 +461,966 @@ pub struct Coords {
     pub max_lon: f64,
 }
 
+impl Coords {
+    /// Range checks. `min_lon > max_lon` is allowed: the box wraps across the antimeridian.
+    pub fn validate(&self) -> Result<(), String> {
+        for (field, value) in [("min_lat", self.min_lat), ("max_lat", self.max_lat)] {
+            if !(-90.0..=90.0).contains(&value) {
+                return Err(format!("coords {} must be between -90 and 90, got {}", field, value));
+            }
+        }
+        for (field, value) in [("min_lon", self.min_lon), ("max_lon", self.max_lon)] {
+            if !(-180.0..=180.0).contains(&value) {
+                return Err(format!("coords {} must be between -180 and 180, got {}", field, value));
+            }
+        }
+        if self.min_lat > self.max_lat {
+            return Err(format!(
+                "coords min_lat ({}) must not be greater than max_lat ({})",
+                self.min_lat, self.max_lat
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn crosses_antimeridian(&self) -> bool {
+        self.min_lon > self.max_lon
+    }
+
+    /// `(lat, lon)` of the middle of the box, across the antimeridian if it wraps.
+    pub fn center(&self) -> (f64, f64) {
+        let lat = (self.min_lat + self.max_lat) / 2.0;
+        let lon = (self.min_lon + self.max_lon) / 2.0;
+
+        match self.crosses_antimeridian() {
+            true if lon > 0.0 => (lat, lon - 180.0),
+            true => (lat, lon + 180.0),
+            false => (lat, lon),
+        }
+    }
+
+    /// `[lon, lat]` corner pairs covering the box, split in two at the antimeridian.
+    pub fn corners(&self) -> Vec<([f64; 2], [f64; 2])> {
+        if self.crosses_antimeridian() {
+            vec![
+                ([self.min_lon, self.min_lat], [180.0, self.max_lat]),
+                ([-180.0, self.min_lat], [self.max_lon, self.max_lat]),
+            ]
+        } else {
+            vec![([self.min_lon, self.min_lat], [self.max_lon, self.max_lat])]
+        }
+    }
+}
+
+/// A ring of `[lon, lat]` positions whose first and last positions are the same.
+pub type Ring = Vec<[f64; 2]>;
+
+/// An outer ring followed by any holes cut out of it.
+pub type Polygon = Vec<Ring>;
+
+/// A search area as GeoJSON: a Polygon or MultiPolygon geometry, a Feature holding one,
+/// or a FeatureCollection of such Features. Positions may carry altitude, which is ignored.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(tag = "type")]
+pub enum Area {
+    Polygon {
+        #[schema(value_type = Vec<Vec<Vec<f64>>>)]
+        #[serde(deserialize_with = "polygon_positions")]
+        coordinates: Polygon,
+    },
+    MultiPolygon {
+        #[schema(value_type = Vec<Vec<Vec<Vec<f64>>>>)]
+        #[serde(deserialize_with = "multi_polygon_positions")]
+        coordinates: Vec<Polygon>,
+    },
+    Feature {
+        #[schema(no_recursion)]
+        geometry: Box<Area>,
+    },
+    FeatureCollection {
+        #[schema(no_recursion)]
+        features: Vec<Area>,
+    },
+}
+
+/// `[lon, lat]` from a GeoJSON position, which may add altitude and more after them.
+fn position<E: serde::de::Error>(values: Vec<f64>) -> Result<[f64; 2], E> {
+    match values[..] {
+        [lon, lat, ..] => Ok([lon, lat]),
+        _ => Err(E::custom(format!("position needs at least 2 numbers, got {}", values.len()))),
+    }
+}
+
+fn polygon_positions<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Polygon, D::Error> {
+    Vec::<Vec<Vec<f64>>>::deserialize(deserializer)?
+        .into_iter()
+        .map(|ring| ring.into_iter().map(position).collect())
+        .collect()
+}
+
+fn multi_polygon_positions<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<Polygon>, D::Error> {
+    Vec::<Vec<Vec<Vec<f64>>>>::deserialize(deserializer)?
+        .into_iter()
+        .map(|polygon| {
+            polygon
+                .into_iter()
+                .map(|ring| ring.into_iter().map(position).collect())
+                .collect()
+        })
+        .collect()
+}
+
+impl Area {
+    pub fn polygons(&self) -> Vec<&Polygon> {
+        match self {
+            Area::Polygon { coordinates } => vec![coordinates],
+            Area::MultiPolygon { coordinates } => coordinates.iter().collect(),
+            Area::Feature { geometry } => geometry.polygons(),
+            Area::FeatureCollection { features } => features.iter().flat_map(Area::polygons).collect(),
+        }
+    }
+
+    /// Checks what the JSON shape can't: GeoJSON nesting, and closed rings of at least
+    /// four positions, in range.
+    pub fn validate(&self) -> Result<(), String> {
+        self.validate_nesting()?;
+
+        let polygons = self.polygons();
+        if polygons.is_empty() {
+            return Err("area has no polygons".to_string());
+        }
+
+        for (p, polygon) in polygons.iter().enumerate() {
+            if polygon.is_empty() {
+                return Err(format!("area polygon {} has no rings", p));
+            }
+
+            for (r, ring) in polygon.iter().enumerate() {
+                if ring.len() < 4 {
+                    return Err(format!("area polygon {} ring {} needs at least 4 positions", p, r));
+                }
+                if ring.first() != ring.last() {
+                    return Err(format!("area polygon {} ring {} must end where it starts", p, r));
+                }
+                if let Some([lon, lat]) = ring
+                    .iter()
+                    .find(|[lon, lat]| !(-180.0..=180.0).contains(lon) || !(-90.0..=90.0).contains(lat))
+                {
+                    return Err(format!("area polygon {} ring {} has out-of-range position [{}, {}]", p, r, lon, lat));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_nesting(&self) -> Result<(), String> {
+        match self {
+            Area::Feature { geometry } if !matches!(**geometry, Area::Polygon { .. } | Area::MultiPolygon { .. }) => {
+                Err("area Feature geometry must be a Polygon or MultiPolygon".to_string())
+            }
+            Area::FeatureCollection { features } => features.iter().try_for_each(|feature| match feature {
+                Area::Feature { .. } => feature.validate_nesting(),
+                _ => Err("area FeatureCollection may only hold Features".to_string()),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The smallest box around every outer ring.
+    pub fn bounds(&self) -> Coords {
+        let mut bounds = Coords {
+            min_lat: f64::INFINITY,
+            max_lat: f64::NEG_INFINITY,
+            min_lon: f64::INFINITY,
+            max_lon: f64::NEG_INFINITY,
+        };
+
+        for [lon, lat] in self.polygons().into_iter().filter_map(|p| p.first()).flatten() {
+            bounds.min_lat = bounds.min_lat.min(*lat);
+            bounds.max_lat = bounds.max_lat.max(*lat);
+            bounds.min_lon = bounds.min_lon.min(*lon);
+            bounds.max_lon = bounds.max_lon.max(*lon);
+        }
+
+        bounds
+    }
+}
+
+/// Position of `courses[i]` as `[lon, lat]`, for box queries.
+pub type BoxPoint = GeomWithData<[f64; 2], usize>;
+
+/// Position of `courses[i]` as a unit vector, for distance queries: straight-line
+/// distance between unit vectors ranks the same as great-circle distance.
+pub type SpherePoint = GeomWithData<[f64; 3], usize>;
+
+/// The loaded courses with the lookups built once per load.
+pub struct CourseCatalog {
+    pub courses: Vec<GolfCourse>,
+    pub by_slug: HashMap<String, usize>,
+    pub box_index: RTree<BoxPoint>,
+    pub sphere_index: RTree<SpherePoint>,
+}
+
+/// Last time each course's tee sheet was fetched successfully, by slug.
+#[derive(Clone, Default)]
+pub struct FetchLog(Arc<RwLock<HashMap<String, DateTime<Utc>>>>);
+
+impl FetchLog {
+    pub fn record(&self, slug: &str) {
+        self.0
+            .write()
+            .expect("fetch log lock poisoned")
+            .insert(slug.to_string(), Utc::now());
+    }
+
+    pub fn last_success(&self, slug: &str) -> Option<DateTime<Utc>> {
+        self.0
+            .read()
+            .expect("fetch log lock poisoned")
+            .get(slug)
+            .copied()
+    }
+}
+
+/// Recently fetched tee sheets, so repeated map views don't refetch every course.
+#[derive(Clone, Default)]
+pub struct TeeTimeCache(Arc<RwLock<HashMap<String, CachedTeeSheet>>>);
+
+/// When a tee sheet was fetched, and its tee times.
+type CachedTeeSheet = (Instant, Vec<TeeTime>);
+
+impl TeeTimeCache {
+    pub const TTL: Duration = Duration::from_secs(60);
+
+    pub fn key(slug: &str, date: &str, players: u32, holes: Option<u32>) -> String {
+        format!("{}/{}/{}/{}", slug, date, players, holes.map_or("any".to_string(), |h| h.to_string()))
+    }
+
+    pub fn get(&self, key: &str) -> Option<Vec<TeeTime>> {
+        self.0
+            .read()
+            .expect("tee time cache lock poisoned")
+            .get(key)
+            .filter(|(fetched, _)| fetched.elapsed() < Self::TTL)
+            .map(|(_, tee_times)| tee_times.clone())
+    }
+
+    pub fn insert(&self, key: String, tee_times: Vec<TeeTime>) {
+        let mut cache = self.0.write().expect("tee time cache lock poisoned");
+
+        cache.retain(|_, (fetched, _)| fetched.elapsed() < Self::TTL);
+        cache.insert(key, (Instant::now(), tee_times));
+    }
+}
+
+/// A saved tee time search, e.g. "2 players near home on weekends".
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Watch {
+    pub id: String,
+    pub name: String,
+    /// The `/tee_times` query, e.g. `{"near": "39.1,-94.6", "players": 2}`.
+    pub query: TeeTimeQuery,
+    pub created: DateTime<Utc>,
+}
+
+/// `POST /watches` body.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NewWatch {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub query: TeeTimeQuery,
+}
+
+/// Saved searches by id. Kept in memory, so they last until the server restarts.
+#[derive(Clone, Default)]
+pub struct Watches(Arc<RwLock<HashMap<String, Watch>>>);
+
+impl Watches {
+    pub fn insert(&self, watch: Watch) {
+        self.0
+            .write()
+            .expect("watch store lock poisoned")
+            .insert(watch.id.clone(), watch);
+    }
+
+    pub fn get(&self, id: &str) -> Option<Watch> {
+        self.0
+            .read()
+            .expect("watch store lock poisoned")
+            .get(id)
+            .cloned()
+    }
+
+    /// Oldest first.
+    pub fn list(&self) -> Vec<Watch> {
+        let mut watches: Vec<Watch> = self.0
+            .read()
+            .expect("watch store lock poisoned")
+            .values()
+            .cloned()
+            .collect();
+        watches.sort_by_key(|w| w.created);
+
+        watches
+    }
+
+    pub fn remove(&self, id: &str) -> Option<Watch> {
+        self.0
+            .write()
+            .expect("watch store lock poisoned")
+            .remove(id)
+    }
+}
+
+/// `/courses/{slug}` body: the catalog record plus what we know about fetching it.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CourseDetail {
+    #[serde(flatten)]
+    pub course: GolfCourse,
+    /// Round lengths the course can be booked for, `null` when the catalog doesn't say.
+    /// Taken out of `course`, which leaves an empty list out.
+    pub hole_options: Option<Vec<u32>>,
+    pub provider: &'static str,
+    pub tee_times_supported: bool,
+    pub last_successful_fetch: Option<DateTime<Utc>>,
+}
+
 #[derive(Clone)]
 pub struct AppState {
-    pub courses: Arc<Vec<GolfCourse>>,
+    pub fetch_log: FetchLog,
+    pub tee_time_cache: TeeTimeCache,
+    pub watches: Watches,
+    pub history: TeeTimeHistory,
+    /// Held while the admin API rewrites a catalog file.
+    pub catalog_write: Arc<tokio::sync::Mutex<()>>,
+    /// Swapped wholesale on reload, so in-flight requests keep the catalog they started with.
+    pub courses: Arc<RwLock<Arc<CourseCatalog>>>,
+    /// `--catalog` paths, re-resolved on every reload so new files in a directory are picked up.
+    pub catalog_paths: Arc<Vec<PathBuf>>,
+}
+
+impl AppState {
+    pub fn courses(&self) -> Arc<CourseCatalog> {
+        self.courses
+            .read()
+            .expect("course catalog lock poisoned")
+            .clone()
+    }
+}
+
+/// JSON error body returned by the API.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiError {
+    #[serde(skip)]
+    pub status: StatusCode,
+    pub error: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<String>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, error: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            error: error.into(),
+            details: Vec::new(),
+        }
+    }
+
+    pub fn with_details(mut self, details: Vec<String>) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self)).into_response()
+    }
+}
+
+/// How `/courses` and `/tee_times` render, from `format=` or else the `Accept` header.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Json,
+    GeoJson,
+    /// Tee times only.
+    Csv,
+    /// Tee times only, one per line.
+    NdJson,
+}
+
+impl OutputFormat {
+    pub const GEOJSON_TYPE: &'static str = "application/geo+json";
+    pub const CSV_TYPE: &'static str = "text/csv";
+    pub const NDJSON_TYPE: &'static str = "application/x-ndjson";
+
+    pub fn negotiate(format: Option<OutputFormat>, accept: Option<&str>) -> OutputFormat {
+        match format {
+            Some(format) => format,
+            None if accept.is_some_and(|a| a.contains(Self::GEOJSON_TYPE)) => OutputFormat::GeoJson,
+            None if accept.is_some_and(|a| a.contains(Self::CSV_TYPE)) => OutputFormat::Csv,
+            None if accept.is_some_and(|a| a.contains(Self::NDJSON_TYPE)) => OutputFormat::NdJson,
+            None => OutputFormat::Json,
+        }
+    }
+}
+
+/// GeoJSON FeatureCollection of course points.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FeatureCollection<P> {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub features: Vec<Feature<P>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Feature<P> {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub geometry: Point,
+    pub properties: P,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Point {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    /// `[lon, lat]`, GeoJSON order.
+    pub coordinates: [f64; 2],
+}
+
+impl<P> FeatureCollection<P> {
+    pub fn new(features: Vec<Feature<P>>) -> Self {
+        FeatureCollection { kind: "FeatureCollection", features }
+    }
+}
+
+impl<P> Feature<P> {
+    pub fn point(lat: f64, lon: f64, properties: P) -> Self {
+        Feature {
+            kind: "Feature",
+            geometry: Point { kind: "Point", coordinates: [lon, lat] },
+            properties,
+        }
+    }
+}
+
+impl<P: Serialize> IntoResponse for FeatureCollection<P> {
+    fn into_response(self) -> Response {
+        ([(header::CONTENT_TYPE, OutputFormat::GEOJSON_TYPE)], Json(self)).into_response()
+    }
 }
 
-#[derive(Debug, Serialize)]
+/// Tee times at one course, as GeoJSON feature properties.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CourseTeeTimes {
+    pub slug: String,
+    pub course: String,
+    pub status: CourseState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub tee_times: usize,
+    pub earliest_tee_time: Option<DateTime<Utc>>,
+    pub cheapest_price_per_player: Option<f64>,
+}
+
+/// Courses close together at a zoom level, as GeoJSON feature properties.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Cluster {
+    pub courses: usize,
+    pub slugs: Vec<String>,
+    pub tee_times: usize,
+    pub cheapest_price_per_player: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Clone, ToSchema)]
 pub struct TeeTime {
     pub course: String,
+    pub slug: String,
     pub tee_time: DateTime<Utc>,
-    pub price: f64,
+    /// The course's time zone, for showing `tee_time` in local time.
+    #[serde(skip)]
+    pub timezone: Tz,
+    pub price_per_player: f64,
+    pub total_for_party: f64,
     pub players: u32,
     pub holes: Option<u32>,
+    /// The provider's rate plan, where it has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate: Option<String>,
     pub lat: f64,
     pub lon: f64,
     pub book_url: String,
 }
 
+impl TeeTime {
+    /// Stays the same across polls for the same slot at the same rate, so feed
+    /// readers don't show it twice and a new price reads as a price change.
+    /// Without a rate plan, the hole count stands in.
+    pub fn entry_id(&self) -> String {
+        let rate = match &self.rate {
+            Some(rate) => slugify(rate),
+            None => format!("{}-holes", self.holes.map_or("any".to_string(), |h| h.to_string())),
+        };
+
+        format!("{}/{}/{}", self.slug, self.tee_time.format("%Y%m%dT%H%MZ"), rate)
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// Wasn't on the tee sheet at the previous poll.
+    Opened,
+    /// Gone from the tee sheet before its start time.
+    Booked,
+    PriceChanged,
+}
+
+/// A difference between two polls of a tee sheet.
+#[derive(Debug, Serialize, Clone, ToSchema)]
+pub struct TeeTimeChange {
+    pub id: String,
+    pub kind: ChangeKind,
+    pub seen: DateTime<Utc>,
+    /// As it is now, or as it was last seen when booked.
+    pub tee_time: TeeTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_price_per_player: Option<f64>,
+}
+
+/// What the poller last saw on each tee sheet, and what changed recently.
+#[derive(Clone)]
+pub struct TeeTimeHistory {
+    state: Arc<RwLock<History>>,
+    /// Every recorded change, as it happens, for live subscribers.
+    live: broadcast::Sender<TeeTimeChange>,
+}
+
+impl Default for TeeTimeHistory {
+    fn default() -> Self {
+        TeeTimeHistory {
+            state: Arc::default(),
+            live: broadcast::channel(Self::LIVE_BUFFER).0,
+        }
+    }
+}
+
+#[derive(Default)]
+struct History {
+    /// Tee times by `entry_id`, per `(slug, date)` tee sheet.
+    sheets: HashMap<(String, NaiveDate), HashMap<String, TeeTime>>,
+    /// Oldest first.
+    changes: VecDeque<TeeTimeChange>,
+}
+
+impl TeeTimeHistory {
+    /// Changes kept for feeds; older ones drop off.
+    pub const MAX_CHANGES: usize = 5000;
+
+    /// Changes a live subscriber can fall behind by before it misses some.
+    pub const LIVE_BUFFER: usize = 1024;
+
+    /// Stores the latest poll of a tee sheet, returning the previous one if there was one.
+    pub fn replace_sheet(
+        &self,
+        slug: &str,
+        date: NaiveDate,
+        tee_times: HashMap<String, TeeTime>,
+    ) -> Option<HashMap<String, TeeTime>> {
+        self.state
+            .write()
+            .expect("tee time history lock poisoned")
+            .sheets
+            .insert((slug.to_string(), date), tee_times)
+    }
+
+    /// Keeps the changes for feeds and passes them on to live subscribers.
+    pub fn record(&self, changes: Vec<TeeTimeChange>) {
+        let mut history = self.state.write().expect("tee time history lock poisoned");
+
+        for change in changes {
+            // No subscribers is fine
+            let _ = self.live.send(change.clone());
+            history.changes.push_back(change);
+        }
+        while history.changes.len() > Self::MAX_CHANGES {
+            history.changes.pop_front();
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TeeTimeChange> {
+        self.live.subscribe()
+    }
+
+    /// The last polled tee times at `slugs`, soonest first.
+    pub fn current(&self, slugs: &HashSet<String>) -> Vec<TeeTime> {
+        let mut tee_times: Vec<TeeTime> = self.state
+            .read()
+            .expect("tee time history lock poisoned")
+            .sheets
+            .iter()
+            .filter(|((slug, _), _)| slugs.contains(slug))
+            .flat_map(|(_, sheet)| sheet.values().cloned())
+            .collect();
+        tee_times.sort_by_key(|tt| tt.tee_time);
+
+        tee_times
+    }
+
+    /// The last poll of one course's tee sheet, if it has been polled.
+    pub fn sheet(&self, slug: &str, date: NaiveDate) -> Option<Vec<TeeTime>> {
+        self.state
+            .read()
+            .expect("tee time history lock poisoned")
+            .sheets
+            .get(&(slug.to_string(), date))
+            .map(|sheet| sheet.values().cloned().collect())
+    }
+
+    /// Forgets tee sheets for days before `today`.
+    pub fn prune(&self, today: NaiveDate) {
+        self.state
+            .write()
+            .expect("tee time history lock poisoned")
+            .sheets
+            .retain(|(_, date), _| *date >= today);
+    }
+
+    /// Newest first.
+    pub fn changes(&self) -> Vec<TeeTimeChange> {
+        self.state
+            .read()
+            .expect("tee time history lock poisoned")
+            .changes
+            .iter()
+            .rev()
+            .cloned()
+            .collect()
+    }
+}
+
+/// A batch search's outcome: its tee times and course statuses, or why it couldn't run.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum BatchResult {
+    Search(TeeTimeSearch),
+    Error(ApiError),
+}
+
+/// A `/ws` message from the client.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Replaces any earlier subscription.
+    Subscribe(Subscription),
+    Unsubscribe,
+}
+
+/// Which tee times a `/ws` client wants to hear about: some courses by slug, the
+/// courses inside `coords`, or both.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct Subscription {
+    #[serde(default)]
+    pub courses: Vec<String>,
+    pub coords: Option<Coords>,
+    /// Any day the poller covers when left out.
+    pub date: Option<NaiveDate>,
+    #[serde(default = "one_player")]
+    pub players: u32,
+    pub holes: Option<u32>,
+}
+
+fn one_player() -> u32 {
+    1
+}
+
+/// A `/ws` message to the client.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Subscribed { courses: Vec<String> },
+    /// The matching tee times as of the last poll, sent after subscribing.
+    Snapshot { tee_times: Vec<TeeTime> },
+    Change(TeeTimeChange),
+    /// The client fell behind and this many changes were dropped; resubscribe for a fresh snapshot.
+    Lagged { missed: u64 },
+    Error { error: String },
+}
+
+/// The outcome of fetching one course's tee sheet for one day.
+#[derive(Debug)]
+pub struct TeeSheet {
+    pub course: GolfCourse,
+    pub date: NaiveDate,
+    /// The round length asked for, if any.
+    pub holes: Option<u32>,
+    pub result: Result<Vec<TeeTime>, String>,
+}
+
+/// `/tee_times/stream` `course` event: one course's tee times for one day.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CourseResult {
+    #[serde(flatten)]
+    pub status: CourseStatus,
+    pub tee_times: Vec<TeeTime>,
+}
+
+/// `/tee_times/stream` `summary` event, sent once every course has finished.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SearchSummary {
+    pub tee_times: usize,
+    pub courses: Vec<CourseStatus>,
+}
+
+/// Progress of a streamed tee time search.
+#[derive(Debug)]
+pub enum SearchUpdate {
+    Course(CourseResult),
+    Done(SearchSummary),
+}
+
+/// A line of `format=ndjson` tee times, tagged by `type`.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NdJsonLine {
+    TeeTime(TeeTime),
+    /// A course whose tee sheet couldn't be fetched.
+    CourseStatus(CourseStatus),
+}
+
+/// `/tee_times` body when `include_status=true`.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TeeTimeSearch {
+    pub tee_times: Vec<TeeTime>,
+    pub courses: Vec<CourseStatus>,
+}
+
+/// `GET /v1/courses` query. Courses are located by at most one of `near`, `area` and
+/// `coords`; without any, the default region is searched.
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
+pub struct CourseQuery {
+    /// Course name search, e.g. `swope`.
+    pub q: Option<String>,
+    /// `lat,lon` to search around, nearest first.
+    #[param(example = "39.1,-94.6")]
+    pub near: Option<String>,
+    /// Distance from `near`, 25 km unless only `k` is given.
+    pub radius_km: Option<f64>,
+    /// Most courses to take from `near`.
+    pub k: Option<usize>,
+    /// Bounding box as JSON.
+    #[param(example = r#"{"min_lat":38.7,"max_lat":39.4,"min_lon":-94.9,"max_lon":-94.2}"#)]
+    pub coords: Option<String>,
+    /// GeoJSON Polygon, MultiPolygon or Feature, as JSON.
+    pub area: Option<String>,
+    pub walkable: Option<bool>,
+    pub driving_range: Option<bool>,
+    pub access: Option<CourseAccess>,
+    /// Leaves out courses closed that day.
+    pub date: Option<NaiveDate>,
+    /// Holes on the layout.
+    pub holes: Option<u32>,
+    /// `json` or `geojson`; the `Accept` header decides when left out.
+    pub format: Option<OutputFormat>,
+}
+
+/// `GET /v1/tee_times` query, also taken by `/v1/tee_times/stream`, `/v1/tee_times.ics`
+/// and each search in a batch. Courses are picked as for `/v1/courses`.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, IntoParams, ToSchema)]
+#[serde(deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
+pub struct TeeTimeQuery {
+    /// Course name search, e.g. `swope`.
+    pub q: Option<String>,
+    /// `lat,lon` to search around.
+    #[param(example = "39.1,-94.6")]
+    pub near: Option<String>,
+    /// Distance from `near`, 25 km unless only `k` is given.
+    pub radius_km: Option<f64>,
+    /// Most courses to take from `near`.
+    pub k: Option<usize>,
+    /// Bounding box as JSON.
+    #[param(example = r#"{"min_lat":38.7,"max_lat":39.4,"min_lon":-94.9,"max_lon":-94.2}"#)]
+    pub coords: Option<String>,
+    /// GeoJSON Polygon, MultiPolygon or Feature, as JSON.
+    pub area: Option<String>,
+    pub walkable: Option<bool>,
+    pub driving_range: Option<bool>,
+    pub access: Option<CourseAccess>,
+    /// Day to search, today when left out.
+    pub date: Option<NaiveDate>,
+    /// Last day to search, up to two weeks on. Not for plain JSON or GeoJSON output.
+    pub end_date: Option<NaiveDate>,
+    /// Party size, 4 when left out.
+    pub players: Option<u32>,
+    /// Round length.
+    pub holes: Option<u32>,
+    /// `json`, `geojson`, `csv` or `ndjson`; the `Accept` header decides when left out.
+    /// `/v1/tee_times` and `/v1/courses/{slug}/tee_times` only.
+    pub format: Option<OutputFormat>,
+    /// Wraps the tee times with every course's status. `/v1/tee_times` and
+    /// `/v1/courses/{slug}/tee_times` only.
+    pub include_status: Option<bool>,
+}
+
+/// `GET /v1/courses/clusters` query.
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
+pub struct ClusterQuery {
+    /// Map zoom level, 0 to 22. Below 10, tee sheets aren't fetched for the counts.
+    pub zoom: u32,
+    /// Course name search, e.g. `swope`.
+    pub q: Option<String>,
+    /// `lat,lon` to search around.
+    #[param(example = "39.1,-94.6")]
+    pub near: Option<String>,
+    /// Distance from `near`, 25 km unless only `k` is given.
+    pub radius_km: Option<f64>,
+    /// Most courses to take from `near`.
+    pub k: Option<usize>,
+    /// Bounding box as JSON, usually the visible map.
+    #[param(example = r#"{"min_lat":38.7,"max_lat":39.4,"min_lon":-94.9,"max_lon":-94.2}"#)]
+    pub coords: Option<String>,
+    /// GeoJSON Polygon, MultiPolygon or Feature, as JSON.
+    pub area: Option<String>,
+    pub walkable: Option<bool>,
+    pub driving_range: Option<bool>,
+    pub access: Option<CourseAccess>,
+    /// Day to count tee times on, today when left out.
+    pub date: Option<NaiveDate>,
+    /// Party size, 4 when left out.
+    pub players: Option<u32>,
+    /// Round length.
+    pub holes: Option<u32>,
+}
+
+/// `GET /v1/courses/{slug}/tee_times` query.
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
+pub struct CourseTeeTimeQuery {
+    /// Day to search, today when left out.
+    pub date: Option<NaiveDate>,
+    /// Last day to search, up to two weeks on. Not for plain JSON or GeoJSON output.
+    pub end_date: Option<NaiveDate>,
+    /// Party size, 4 when left out.
+    pub players: Option<u32>,
+    /// Round length.
+    pub holes: Option<u32>,
+    /// `json`, `geojson`, `csv` or `ndjson`; the `Accept` header decides when left out.
+    pub format: Option<OutputFormat>,
+    /// Wraps the tee times with the course's status.
+    pub include_status: Option<bool>,
+}
+
+/// `GET /v1/courses/{slug}/feed.atom` query.
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
+pub struct FeedQuery {
+    /// Party size the tee times must fit, 1 when left out.
+    pub players: Option<u32>,
+    /// Round length.
+    pub holes: Option<u32>,
+}
+
+/// One search in a `POST /v1/tee_times/batch` body: the `/tee_times` parameters and an `id`.
+// Only documents the body: `deny_unknown_fields` doesn't hold under `flatten`, so
+// entries are read by `parse_batch_search` instead.
+#[derive(Debug, ToSchema)]
+pub struct BatchSearch {
+    /// Keys the search's result; its position in the batch otherwise.
+    pub id: Option<String>,
+    #[serde(flatten)]
+    pub query: TeeTimeQuery,
+}
+
+/// The course-picking parameters that the course, tee time and cluster queries share.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CourseSelection<'a> {
+    pub q: Option<&'a str>,
+    pub near: Option<&'a str>,
+    pub radius_km: Option<f64>,
+    pub k: Option<usize>,
+    pub coords: Option<&'a str>,
+    pub area: Option<&'a str>,
+    pub walkable: Option<bool>,
+    pub driving_range: Option<bool>,
+    pub access: Option<CourseAccess>,
+}
+
+impl CourseQuery {
+    pub fn selection(&self) -> CourseSelection<'_> {
+        CourseSelection {
+            q: self.q.as_deref(),
+            near: self.near.as_deref(),
+            radius_km: self.radius_km,
+            k: self.k,
+            coords: self.coords.as_deref(),
+            area: self.area.as_deref(),
+            walkable: self.walkable,
+            driving_range: self.driving_range,
+            access: self.access,
+        }
+    }
+}
+
+impl TeeTimeQuery {
+    pub fn selection(&self) -> CourseSelection<'_> {
+        CourseSelection {
+            q: self.q.as_deref(),
+            near: self.near.as_deref(),
+            radius_km: self.radius_km,
+            k: self.k,
+            coords: self.coords.as_deref(),
+            area: self.area.as_deref(),
+            walkable: self.walkable,
+            driving_range: self.driving_range,
+            access: self.access,
+        }
+    }
+}
+
+impl ClusterQuery {
+    pub fn selection(&self) -> CourseSelection<'_> {
+        CourseSelection {
+            q: self.q.as_deref(),
+            near: self.near.as_deref(),
+            radius_km: self.radius_km,
+            k: self.k,
+            coords: self.coords.as_deref(),
+            area: self.area.as_deref(),
+            walkable: self.walkable,
+            driving_range: self.driving_range,
+            access: self.access,
+        }
+    }
+}
+
+/// A single course's search is a `/tee_times` search with no course parameters.
+impl From<CourseTeeTimeQuery> for TeeTimeQuery {
+    fn from(query: CourseTeeTimeQuery) -> Self {
+        TeeTimeQuery {
+            date: query.date,
+            end_date: query.end_date,
+            players: query.players,
+            holes: query.holes,
+            format: query.format,
+            include_status: query.include_status,
+            ..TeeTimeQuery::default()
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct GolfBackResponse {
     #[serde(default)]
@@ -74,7 +1444,7 @@ pub struct GolfBackTeeTime {
     // pub has9Holes: bool,
     // pub hasDailyGimmeV2: bool,
     // pub hasDeal: bool,
-    pub holes: Vec<u32>,
+    // pub holes: Vec<u32>, // rates carry their own holes
     pub id: String,
     // pub isAvailable: bool,
     // pub localDateTime: String,
@@ -96,7 +1466,7 @@ pub struct GolfBackRate {
     // pub description: String, // "All you can play, up to 18 holes",
     // pub feeDisplay: f64, // 0.0,
     // pub hasCartIncluded: bool, // true,
-    // pub holes: u32, // 18,
+    pub holes: u32, // 18,
     // pub isDailyGimmeV2: bool, // false,
     // pub isDeal: bool, // false,
     // pub isGimme: bool, // false,
@@ -163,6 +1533,7 @@ pub struct TeeItUpTeeTime {
 
 #[derive(Debug, Deserialize)]
 pub struct TeeItUpRate {
+    pub name: Option<String>,
     pub holes: u32,
     #[serde(rename = "greenFeeCart")]
     pub green_fee_cart: Option<u64>,
@@ -173,4 +1544,95 @@ pub struct TeeItUpRate {
 pub struct TeeItUpPromotion {
     #[serde(rename = "greenFeeCart")]
     pub green_fee_cart: u64,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coords(min_lat: f64, max_lat: f64, min_lon: f64, max_lon: f64) -> Coords {
+        Coords { min_lat, max_lat, min_lon, max_lon }
+    }
+
+    #[test]
+    fn coords_must_be_in_range_and_not_upside_down() {
+        assert!(coords(38.7, 39.4, -94.9, -94.2).validate().is_ok());
+        assert!(coords(39.4, 38.7, -94.9, -94.2).validate().is_err());
+        assert!(coords(-91.0, 39.4, -94.9, -94.2).validate().is_err());
+        assert!(coords(38.7, 90.5, -94.9, -94.2).validate().is_err());
+        assert!(coords(38.7, 39.4, -180.5, -94.2).validate().is_err());
+        assert!(coords(38.7, 39.4, -94.9, 181.0).validate().is_err());
+    }
+
+    #[test]
+    fn coords_may_wrap_across_the_antimeridian() {
+        let wrapping = coords(-20.0, -10.0, 170.0, -170.0);
+
+        assert!(wrapping.validate().is_ok());
+        assert!(wrapping.crosses_antimeridian());
+        assert_eq!(
+            wrapping.corners(),
+            vec![([170.0, -20.0], [180.0, -10.0]), ([-180.0, -20.0], [-170.0, -10.0])],
+        );
+        assert_eq!(wrapping.center(), (-15.0, 180.0));
+        assert_eq!(coords(-20.0, -10.0, 176.0, -174.0).center(), (-15.0, -179.0));
+        assert_eq!(coords(38.7, 39.4, -94.9, -94.2).corners().len(), 1);
+    }
+
+    #[test]
+    fn area_positions_may_carry_altitude() {
+        let area: Area = serde_json::from_str(
+            r#"{"type": "Polygon", "coordinates": [[[-95, 39, 250], [-94, 39, 260.5], [-94, 40, 0], [-95, 39, 250]]]}"#,
+        ).unwrap();
+
+        assert_eq!(area.polygons()[0][0], vec![[-95.0, 39.0], [-94.0, 39.0], [-94.0, 40.0], [-95.0, 39.0]]);
+        assert!(area.validate().is_ok());
+        assert!(serde_json::from_str::<Area>(r#"{"type": "Polygon", "coordinates": [[[-95], [-94, 39], [-94, 40], [-95]]]}"#).is_err());
+    }
+
+    #[test]
+    fn feature_collections_hold_features_only() {
+        let square = r#"{"type": "Polygon", "coordinates": [[[-95, 39], [-94, 39], [-94, 40], [-95, 39]]]}"#;
+        let collection: Area = serde_json::from_str(&format!(
+            r#"{{"type": "FeatureCollection", "features": [{{"type": "Feature", "geometry": {square}}}, {{"type": "Feature", "geometry": {square}}}]}}"#,
+        )).unwrap();
+        let bare: Area = serde_json::from_str(&format!(r#"{{"type": "FeatureCollection", "features": [{square}]}}"#)).unwrap();
+
+        assert_eq!(collection.polygons().len(), 2);
+        assert!(collection.validate().is_ok());
+        assert_eq!(bare.validate(), Err("area FeatureCollection may only hold Features".to_string()));
+    }
+
+    #[test]
+    fn ndjson_lines_say_what_they_hold() {
+        let tee_time = TeeTime {
+            course: "Swope Memorial".to_string(),
+            slug: "swope-memorial".to_string(),
+            tee_time: "2026-05-02T13:10:00Z".parse().unwrap(),
+            timezone: chrono_tz::America::Chicago,
+            price_per_player: 35.0,
+            total_for_party: 70.0,
+            players: 2,
+            holes: Some(18),
+            rate: None,
+            lat: 38.99,
+            lon: -94.53,
+            book_url: "https://example.com".to_string(),
+        };
+        let status = CourseStatus {
+            slug: "swope-memorial".to_string(),
+            course: "Swope Memorial".to_string(),
+            date: NaiveDate::from_ymd_opt(2026, 5, 2).unwrap(),
+            status: CourseState::Error,
+            reason: Some("HTTP 503".to_string()),
+        };
+
+        let line = serde_json::to_value(NdJsonLine::TeeTime(tee_time)).unwrap();
+        assert_eq!(line["type"], "tee_time");
+        assert_eq!(line["slug"], "swope-memorial");
+
+        let line = serde_json::to_value(NdJsonLine::CourseStatus(status)).unwrap();
+        assert_eq!(line["type"], "course_status");
+        assert_eq!(line["status"], "error");
+    }
+}
@@ -1,18 +1,39 @@
+use std::sync::LazyLock;
+use std::collections::HashMap;
+
 use regex::Regex;
 use scraper::{Html, Selector};
-use chrono::{NaiveDateTime, Utc, TimeZone, DateTime};
-use chrono_tz::America::Chicago;
+use chrono::{NaiveDate, NaiveDateTime, Utc, TimeZone, DateTime};
 use reqwest::{
     Client,
     Response
 };
 
 use futures::future::join_all;
+use futures::stream::{self, Stream, StreamExt};
 
 use crate::structs::{
+    Cluster,
+    CourseResult,
+    CourseState,
+    CourseStatus,
+    CourseTeeTimes,
+    Feature,
+    FeatureCollection,
+    FetchLog,
+    SearchSummary,
+    SearchUpdate,
+    TeeSheet,
     TeeTime,
+    TeeTimeCache,
+    TeeTimeHistory,
+    TeeTimeChange,
     GolfCourse,
-    CourseId,
+    Provider,
+    BookATeeTimeId,
+    GolfBackId,
+    ForeUpCourseId,
+    TeeItUpCourseId,
     GolfBackResponse,
     GolfBackTeeTime,
     GolfBackRate,
@@ -20,6 +41,35 @@ use crate::structs::{
     TeeItUpResponse,
 };
 
+/// Rounds a dollar amount to whole cents. Each provider module turns its own price
+/// quote into `price_per_player` and `total_for_party` with this, so prices compare
+/// honestly across platforms whatever the provider quoted.
+pub fn round_cents(dollars: f64) -> f64 {
+    (dollars * 100.0).round() / 100.0
+}
+
+/// Booking link query parameters that carry the party size, across providers.
+static PARTY_SIZE_PARAM: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"([?&#](?:players|golfers|selectedPlayers)=)\d+").expect("party size pattern is valid")
+});
+
+/// A tee time fetched for a smaller party, re-totaled and re-linked for `players`.
+pub fn for_party(tee_time: &TeeTime, players: u32) -> TeeTime {
+    let price_per_player = tee_time.price_per_player;
+    let total_for_party = round_cents(price_per_player * players as f64);
+
+    let book_url = PARTY_SIZE_PARAM
+        .replace_all(&tee_time.book_url, format!("${{1}}{}", players))
+        .into_owned();
+
+    TeeTime {
+        price_per_player,
+        total_for_party,
+        book_url,
+        ..tee_time.clone()
+    }
+}
+
 pub mod book_a_tee_time {
     pub use super::*;
 
@@ -27,26 +77,26 @@ pub mod book_a_tee_time {
     pub async fn fetch(
         client: &Client,
         course: &GolfCourse,
+        course_id: &BookATeeTimeId,
         date: &str,
         players: u32,
-    ) -> Vec<TeeTime> {
-        let course_id: String = match &course.id {
-            CourseId::Number(n) => n.to_string(),
-            CourseId::String(s) => s.clone(),
-            CourseId::Verbose(v) => v.id.to_string(),
-        };
-
-        let url: String = format!(
-            "https://bookateetime.teequest.com/search/{}/{date}?selectedPlayers={players}&selectedHoles=18",
-            course_id
+        holes: Option<u32>,
+    ) -> Result<Vec<TeeTime>, String> {
+        // Without selectedHoles the search covers every round length
+        let mut url: String = format!(
+            "https://bookateetime.teequest.com/search/{}/{date}?selectedPlayers={players}",
+            course_id.0,
         );
+        if let Some(holes) = holes {
+            url.push_str(&format!("&selectedHoles={}", holes));
+        }
         // println!("url: {}", &url);
 
         let response: Response = match client.get(&url).send().await {
             Ok(r) => r,
             Err(e) => {
                 eprintln!("[bookateetime] HTTP error {}: {}", course.name, e);
-                return vec![];
+                return Err(format!("HTTP error: {}", e));
             }
         };
 
@@ -54,11 +104,17 @@ pub mod book_a_tee_time {
             Ok(t) => t,
             Err(e) => {
                 eprintln!("[bookateetime] Body error {}: {}", course.name, e);
-                return vec![];
+                return Err(format!("body error: {}", e));
             }
         };
 
-        let document: Html = Html::parse_document(&body);
+        Ok(parse(course, &body, players))
+    }
+
+    /// Tee times on a search results page. `data-price` is the per-golfer rate, so
+    /// the party pays it once per player.
+    pub fn parse(course: &GolfCourse, body: &str, players: u32) -> Vec<TeeTime> {
+        let document: Html = Html::parse_document(body);
         let tee_time_selector: Selector = Selector::parse("div.tee-time").unwrap();
         let holes_selector: Selector = Selector::parse("div.tee-time__book > span").unwrap();
         let link_selector: Selector = Selector::parse("a.btn").unwrap();
@@ -83,13 +139,15 @@ pub mod book_a_tee_time {
 
                 let tee_time_str = div.value().attr("data-date-time")?;
                 let price: f64 = div.value().attr("data-price")?.parse().ok()?;
+                let price_per_player = round_cents(price);
+                let total_for_party = round_cents(price * players as f64);
                 let players_avail: u32 = div.value().attr("data-available")?.parse().ok()?;
 
                 let naive = NaiveDateTime::parse_from_str(tee_time_str, "%Y%m%d%H%M").ok()?;
                 
-                // Interpret as Chicago time (CST/CDT aware)
-                let central_dt = Chicago.from_local_datetime(&naive).single()?;
-                let tee_time = central_dt.with_timezone(&Utc);
+                // Interpret in the course's time zone (DST aware)
+                let local_dt = course.timezone().from_local_datetime(&naive).single()?;
+                let tee_time = local_dt.with_timezone(&Utc);
 
                 let href = div
                     .select(&link_selector)
@@ -99,10 +157,14 @@ pub mod book_a_tee_time {
 
                 Some(TeeTime {
                     course: course.name.clone(),
+                    slug: course.slug.clone(),
                     tee_time,
-                    price,
+                    timezone: course.timezone(),
+                    price_per_player,
+                    total_for_party,
                     players: players_avail,
                     holes,
+                    rate: None,
                     lat: course.lat,
                     lon: course.lon,
                     book_url: format!("https://bookateetime.teequest.com{}", href),
@@ -111,26 +173,6 @@ pub mod book_a_tee_time {
             .collect()
     }
 
-
-    pub async fn search(
-        courses: &[&GolfCourse],
-        date: &str,
-        players: u32,
-    ) -> Vec<TeeTime> {
-        let client = Client::new();
-
-        let tasks: Vec<_> = courses
-            .iter()
-            .filter(|c| c.source == "bookateetime")
-            .map(|course| self::fetch(&client, course, date, players))
-            .collect();
-
-        join_all(tasks)
-            .await
-            .into_iter()
-            .flatten()
-            .collect()
-    }
 }
 
 
@@ -140,14 +182,12 @@ pub mod golfback {
     pub async fn fetch(
         client: &Client,
         course: &GolfCourse,
+        course_id: &GolfBackId,
         date: &str,
         players: u32,
-    ) -> Vec<TeeTime> {
-        let course_id = match &course.id {
-            CourseId::Number(n) => n.to_string(),
-            CourseId::String(s) => s.clone(),
-            CourseId::Verbose(v) => v.id.to_string(),
-        };
+        holes: Option<u32>,
+    ) -> Result<Vec<TeeTime>, String> {
+        let course_id = &course_id.0;
 
         let url = format!(
             "https://api.golfback.com/api/v1/courses/{}/date/{}/teetimes",
@@ -157,7 +197,8 @@ pub mod golfback {
         let body = serde_json::json!({
             "date": date,
             "course_id": course_id,
-            "players": players
+            "players": players,
+            "holes": holes
         });
 
         let response = match client
@@ -171,73 +212,80 @@ pub mod golfback {
             Ok(r) => r,
             Err(e) => {
                 eprintln!("[GolfBack] {} HTTP error: {}", course.name, e);
-                return vec![];
+                return Err(format!("HTTP error: {}", e));
             }
         };
 
-        let raw_json: serde_json::Value = match response.json().await {
-            Ok(val) => val,
+        let body = match response.text().await {
+            Ok(t) => t,
             Err(e) => {
-                eprintln!("[GolfBack] {} JSON decode error: {}", course.name, e);
-                return vec![];
+                eprintln!("[GolfBack] {} body error: {}", course.name, e);
+                return Err(format!("body error: {}", e));
             }
         };
 
-        // Now try parsing into struct
-        let parsed: GolfBackResponse = match serde_json::from_value(raw_json.clone()) {
+        parse(course, course_id, date, &body, players, holes)
+    }
+
+    /// Tee times in a tee time search response. A rate's `price` is per player.
+    pub fn parse(
+        course: &GolfCourse,
+        course_id: &str,
+        date: &str,
+        body: &str,
+        players: u32,
+        holes: Option<u32>,
+    ) -> Result<Vec<TeeTime>, String> {
+        let parsed: GolfBackResponse = match serde_json::from_str(body) {
             Ok(p) => p,
             Err(e) => {
                 eprintln!("[GolfBack] {} STRUCT PARSE ERROR: {}", course.name, e);
-                return vec![];
+                return Err(format!("unexpected response: {}", e));
             }
         };
 
-        parsed
+        let tee_times = parsed
             .data
             .into_iter()
             .filter_map(|tt: GolfBackTeeTime| {
-                let first_rate: &GolfBackRate = tt.rates.first()?;
+                // The rate for the requested round, so price and booking link match it
+                let rate: &GolfBackRate = tt
+                    .rates
+                    .iter()
+                    .find(|r| holes.is_none_or(|h| r.holes == h))?;
+                let tee_holes = rate.holes;
                 let parsed_dt = chrono::DateTime::parse_from_rfc3339(&tt.date_time).ok()?;
                 let tee_time = parsed_dt.with_timezone(&Utc);
 
+                let price_per_player = round_cents(rate.price);
+                let total_for_party = round_cents(rate.price * players as f64);
+
                 Some(TeeTime {
                     course: course.name.clone(),
+                    slug: course.slug.clone(),
                     tee_time,
-                    price: first_rate.price,
+                    timezone: course.timezone(),
+                    price_per_player,
+                    total_for_party,
                     players: tt.players_max,
-                    holes: tt.holes.into_iter().max(),
+                    holes: Some(tee_holes),
+                    rate: Some(rate.rate_plan_id.clone()),
                     lat: course.lat,
                     lon: course.lon,
                     book_url: format!(
-                        "https://golfback.com/#/course/{}/date/{}/teetime/{}?rateId={}&holes=18&players={}",
+                        "https://golfback.com/#/course/{}/date/{}/teetime/{}?rateId={}&holes={}&players={}",
                         course_id,
                         date,
                         tt.id,
-                        first_rate.rate_plan_id,
+                        rate.rate_plan_id,
+                        tee_holes,
                         players
                     ),
                 })
             })
-        .collect()
-    }
-
-    pub async fn search(
-        courses: &[&GolfCourse],
-        date: &str,
-        players: u32,
-    ) -> Vec<TeeTime> {
-        let client = Client::new();
-
-        let tasks = courses
-            .iter()
-            .filter(|c| c.source == "golfback")
-            .map(|course| self::fetch(&client, course, date, players));
+        .collect();
 
-        join_all(tasks)
-            .await
-            .into_iter()
-            .flatten()
-            .collect()
+        Ok(tee_times)
     }
 }
 
@@ -248,35 +296,38 @@ pub mod foreup {
     pub async fn fetch(
         client: &Client,
         course: &GolfCourse,
+        foreup_id: &ForeUpCourseId,
         date: &str,
         players: u32,
-    ) -> Vec<TeeTime> {
+        holes: Option<u32>,
+    ) -> Result<Vec<TeeTime>, String> {
         // Convert date to MM-DD-YYYY for ForeUp
         let flip_date = match chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d") {
             Ok(d) => d.format("%m-%d-%Y").to_string(),
             Err(e) => {
                 eprintln!("[ForeUp] {} date parse error: {}", course.name, e);
-                return vec![];
+                return Err(format!("invalid date: {}", e));
             }
         };
 
+        let holes_param = holes
+            .map(|h| h.to_string())
+            .unwrap_or_else(|| "all".to_string());
+
         let url = format!(
-            "https://foreupsoftware.com/index.php/api/booking/times?time=all&date={}&holes=all&players={}&booking_class=14824&schedule_id={}&api_key=no_limits",
-            flip_date, players, match &course.id {
-                CourseId::Number(n) => n.to_string(),
-                CourseId::String(s) => s.clone(),
-                CourseId::Verbose(v) => v.id.to_string(),
-            }
+            "https://foreupsoftware.com/index.php/api/booking/times?time=all&date={}&holes={}&players={}&booking_class={}&schedule_id={}&api_key=no_limits",
+            flip_date, holes_param, players, foreup_id.booking_class, foreup_id.schedule_id
+        );
+
+        let booking_page = format!(
+            "https://foreupsoftware.com/index.php/booking/{}/{}",
+            foreup_id.facility_id, foreup_id.schedule_id
         );
 
         let response = match client
             .get(&url)
             .header("User-Agent", "Mozilla/5.0")
-            .header("Referer", format!("https://foreupsoftware.com/index.php/booking/{}/7340", match &course.id {
-                CourseId::Number(n) => n.to_string(),
-                CourseId::String(s) => s.clone(),
-                CourseId::Verbose(v) => v.id.to_string(),
-            }))
+            .header("Referer", &booking_page)
             .header("Content-Type", "application/json")
             .send()
             .await
@@ -284,76 +335,65 @@ pub mod foreup {
             Ok(r) => r,
             Err(e) => {
                 eprintln!("[ForeUp] {} HTTP error: {}", course.name, e);
-                return vec![];
+                return Err(format!("HTTP error: {}", e));
             }
         };
 
-        let raw_json: serde_json::Value = match response.json().await {
-            Ok(val) => val,
+        let body = match response.text().await {
+            Ok(t) => t,
             Err(e) => {
-                eprintln!("[ForeUp] {} JSON decode error: {}", course.name, e);
-                return vec![];
+                eprintln!("[ForeUp] {} body error: {}", course.name, e);
+                return Err(format!("body error: {}", e));
             }
         };
 
-        // let body_text = response.text().await.unwrap_or_default();
-        // println!("[ForeUp] {} RAW RESPONSE:\n{}", course.name, body_text);
+        // println!("[ForeUp] {} RAW RESPONSE:\n{}", course.name, body);
+
+        parse(course, &booking_page, &body, players)
+    }
 
+    /// Tee times in a booking times response. `green_fee` and `cart_fee` are both
+    /// charged per player.
+    pub fn parse(course: &GolfCourse, booking_page: &str, body: &str, players: u32) -> Result<Vec<TeeTime>, String> {
         // Parse into a vector of ForeUpTeeTime
-        let parsed: Vec<ForeUpTeeTime> = match serde_json::from_value(raw_json) {
+        let parsed: Vec<ForeUpTeeTime> = match serde_json::from_str(body) {
             Ok(v) => v,
             Err(e) => {
                 eprintln!("[ForeUp] {} STRUCT PARSE ERROR: {}", course.name, e);
-                return vec![];
+                return Err(format!("unexpected response: {}", e));
             }
         };
 
-        parsed
+        let tee_times = parsed
             .into_iter()
             .filter_map(|tt| {
-                // Interpret as Chicago time (CST/CDT aware)
+                // Interpret in the course's time zone (DST aware)
                 let naive = chrono::NaiveDateTime::parse_from_str(&tt.time, "%Y-%m-%d %H:%M").ok()?;
-                let central_dt = Chicago.from_local_datetime(&naive).single()?;
-                let tee_time = central_dt.with_timezone(&Utc);
+                let local_dt = course.timezone().from_local_datetime(&naive).single()?;
+                let tee_time = local_dt.with_timezone(&Utc);
+
+                let per_player = tt.green_fee + tt.cart_fee;
+                let price_per_player = round_cents(per_player);
+                let total_for_party = round_cents(per_player * players as f64);
 
                 Some(TeeTime {
                     course: course.name.clone(),
+                    slug: course.slug.clone(),
                     tee_time,
-                    price: tt.green_fee + tt.cart_fee,
+                    timezone: course.timezone(),
+                    price_per_player,
+                    total_for_party,
                     players: tt.available_spots,
                     holes: Some(tt.holes),
+                    rate: None,
                     lat: course.lat,
                     lon: course.lon,
-                    book_url: format!(
-                        "https://foreupsoftware.com/index.php/booking/22857/{}#/teetimes",
-                        match &course.id {
-                            CourseId::Number(n) => n.to_string(),
-                            CourseId::String(s) => s.clone(),
-                            CourseId::Verbose(v) => v.id.to_string(),
-                        }
-                    ),
+                    book_url: format!("{}#/teetimes", booking_page),
                 })
             })
-            .collect()
-    }
-
-    pub async fn search(
-        courses: &[&GolfCourse],
-        date: &str,
-        players: u32,
-    ) -> Vec<TeeTime> {
-        let client = Client::new();
-
-        let tasks = courses
-            .iter()
-            .filter(|c| c.source == "foreup")
-            .map(|course| self::fetch(&client, course, date, players));
+            .collect();
 
-        join_all(tasks)
-            .await
-            .into_iter()
-            .flatten()
-            .collect()
+        Ok(tee_times)
     }
 }
 
@@ -363,62 +403,91 @@ pub mod teeitup {
     pub async fn fetch(
         client: &Client,
         course: &GolfCourse,
+        teeitup_id: &TeeItUpCourseId,
         date: &str,
-        // players: u32,
-    ) -> Vec<TeeTime> {
-        let verbose = match &course.id {
-            CourseId::Verbose(v) => v,
-            _ => return vec![],
-        };
+        players: u32,
+        holes: Option<u32>,
+    ) -> Result<Vec<TeeTime>, String> {
 
-        let response = match client
+        let mut request = client
             .get("https://phx-api-be-east-1b.kenna.io/v2/tee-times")
             .query(&[
                 ("date", date),
-                ("facilityIds", &verbose.id.to_string()),
-            ])
+                ("facilityIds", &teeitup_id.id.to_string()),
+                ("golfers", &players.to_string()),
+            ]);
+        if let Some(holes) = holes {
+            request = request.query(&[("holes", holes)]);
+        }
+
+        let response = match request
             .header("Accept", "application/json, text/plain, */*")
-            .header("Origin", &verbose.url)
-            .header("Referer", &verbose.url)
+            .header("Origin", &teeitup_id.url)
+            .header("Referer", &teeitup_id.url)
             .header("User-Agent", "Mozilla/5.0")
-            .header("X-Be-Alias", &verbose.alias)
+            .header("X-Be-Alias", &teeitup_id.alias)
             .send()
             .await
         {
             Ok(r) => r,
             Err(e) => {
                 eprintln!("[TeeItUp] {} HTTP error: {}", course.name, e);
-                return vec![];
+                return Err(format!("HTTP error: {}", e));
+            }
+        };
+
+        let body = match response.text().await {
+            Ok(t) => t,
+            Err(e) => {
+                eprintln!("[TeeItUp] {} body error: {}", course.name, e);
+                return Err(format!("body error: {}", e));
             }
         };
 
-        let raw_json: serde_json::Value = match response.json().await {
+        parse(course, teeitup_id, date, &body, players, holes)
+    }
+
+    /// Tee times in a tee time search response. A rate's `greenFeeCart` is the
+    /// per-golfer price in cents, so the party's total is that times `players`.
+    pub fn parse(
+        course: &GolfCourse,
+        teeitup_id: &TeeItUpCourseId,
+        date: &str,
+        body: &str,
+        players: u32,
+        holes: Option<u32>,
+    ) -> Result<Vec<TeeTime>, String> {
+        let raw_json: serde_json::Value = match serde_json::from_str(body) {
             Ok(v) => v,
             Err(e) => {
                 eprintln!("[TeeItUp] {} JSON parse error: {}", course.name, e);
-                return vec![];
+                return Err(format!("JSON decode error: {}", e));
             }
         };
 
         // API returns an array where [0]['teetimes']
         let first = match raw_json.get(0) {
             Some(v) => v,
-            None => return vec![],
+            None => return Ok(vec![]),
         };
 
         let parsed: TeeItUpResponse = match serde_json::from_value(first.clone()) {
             Ok(p) => p,
             Err(e) => {
                 eprintln!("[TeeItUp] {} STRUCT parse error: {}", course.name, e);
-                return vec![];
+                return Err(format!("unexpected response: {}", e));
             }
         };
 
-        parsed
+        let tee_times = parsed
             .teetimes
             .into_iter()
             .filter_map(|tt| {
-                let rate = tt.rates.first()?;
+                // Pick the rate for the requested hole count, if any
+                let rate = tt
+                    .rates
+                    .iter()
+                    .find(|r| holes.is_none_or(|h| r.holes == h))?;
 
                 // Promotion overrides base rate
                 let price_cents = if let Some(promo) = &rate.promotion {
@@ -427,7 +496,8 @@ pub mod teeitup {
                     rate.green_fee_cart?
                 };
 
-                let price = price_cents as f64 / 100.0;
+                let price_per_player = round_cents(price_cents as f64 / 100.0);
+                let total_for_party = round_cents(price_per_player * players.max(1) as f64);
 
                 let tee_time = DateTime::parse_from_rfc3339(&tt.teetime)
                     .or_else(|_| DateTime::parse_from_str(&tt.teetime, "%Y-%m-%d %H:%M:%S"))
@@ -436,56 +506,754 @@ pub mod teeitup {
 
                 Some(TeeTime {
                     course: course.name.clone(),
+                    slug: course.slug.clone(),
                     tee_time,
-                    price,
+                    timezone: course.timezone(),
+                    price_per_player,
+                    total_for_party,
                     players: tt.max_players,
                     holes: Some(rate.holes),
+                    rate: rate.name.clone(),
                     lat: course.lat,
                     lon: course.lon,
                     book_url: format!(
-                        "{}/?course={}&date={}&max=9999",
-                        verbose.url, verbose.id, date
+                        "{}/?course={}&date={}&golfers={}&holes={}&max=9999",
+                        teeitup_id.url, teeitup_id.id, date, players, rate.holes
                     ),
                 })
             })
-            .collect()
+            .collect();
+
+        Ok(tee_times)
     }
+}
 
-    pub async fn search(
-        courses: &[&GolfCourse],
-        date: &str,
-        // players: u32,
-    ) -> Vec<TeeTime> {
-        let client = Client::new();
 
-        let tasks = courses
-            .iter()
-            .filter(|c| c.source == "teeitup")
-            .map(|course| fetch(&client, course, date)); //, players));
+/// Round lengths a tee time search can ask for.
+pub const ROUND_LENGTHS: [u32; 2] = [9, 18];
 
-        join_all(tasks)
-            .await
-            .into_iter()
-            .flatten()
-            .collect()
+/// Passes `holes` through when it's a round length providers book, if given at all.
+pub fn check_holes(holes: Option<u32>) -> Result<Option<u32>, String> {
+    match holes {
+        Some(h) if !ROUND_LENGTHS.contains(&h) => Err(format!("holes must be 9 or 18, got {}", h)),
+        _ => Ok(holes),
+    }
+}
+
+/// Providers differ in whether they filter by party size and hole count upstream,
+/// so every result is checked against the request the same way here.
+pub fn fits_request(tee_time: &TeeTime, players: u32, holes: Option<u32>) -> bool {
+    tee_time.players >= players
+        && holes.is_none_or(|h| tee_time.holes.is_none_or(|tee_holes| tee_holes == h))
+}
+
+pub async fn fetch_course(
+    client: &Client,
+    course: &GolfCourse,
+    date: &str,
+    players: u32,
+    holes: Option<u32>,
+) -> Result<Vec<TeeTime>, String> {
+    match &course.provider {
+        Provider::BookATeeTime(id) => book_a_tee_time::fetch(client, course, id, date, players, holes).await,
+        Provider::GolfBack(id) => golfback::fetch(client, course, id, date, players, holes).await,
+        Provider::ForeUp(id) => foreup::fetch(client, course, id, date, players, holes).await,
+        Provider::TeeItUp(id) => teeitup::fetch(client, course, id, date, players, holes).await,
+        // later: chronogolf, loner and cps
+        Provider::ChronoGolf(_) | Provider::Loner(_) | Provider::Cps(_) => Err(format!(
+            "{} tee sheets are not supported yet",
+            course.provider.source()
+        )),
     }
 }
 
+/// How many tee sheets a streamed search fetches at once.
+const MAX_CONCURRENT_FETCHES: usize = 16;
+
+/// Fetches the tee sheet for each `(course, date)`, a few at a time, yielding each
+/// as soon as it arrives, so results come in completion order rather than request order.
+pub fn stream_tee_sheets(
+    fetch_log: FetchLog,
+    sheets: Vec<(GolfCourse, NaiveDate)>,
+    players: u32,
+    holes: Option<u32>,
+) -> impl Stream<Item = TeeSheet> {
+    let requests = sheets
+        .into_iter()
+        .map(|(course, date)| (course, date, players, holes))
+        .collect();
+
+    fetch_tee_sheets(fetch_log, requests)
+}
+
+/// `stream_tee_sheets` with a party size and round length per tee sheet.
+pub fn fetch_tee_sheets(
+    fetch_log: FetchLog,
+    requests: Vec<(GolfCourse, NaiveDate, u32, Option<u32>)>,
+) -> impl Stream<Item = TeeSheet> {
+    let client = Client::new();
+
+    stream::iter(requests)
+        .map(move |(course, date, players, holes)| {
+            let client = client.clone();
+            let fetch_log = fetch_log.clone();
+
+            async move {
+                let day = date.format("%Y-%m-%d").to_string();
+                let result = fetch_course(&client, &course, &day, players, holes)
+                    .await
+                    .map(|mut tee_times| {
+                        fetch_log.record(&course.slug);
+                        tee_times.retain(|tt| fits_request(tt, players, holes));
+                        tee_times
+                    });
+
+                TeeSheet { course, date, holes, result }
+            }
+        })
+        .buffer_unordered(MAX_CONCURRENT_FETCHES)
+}
+
+/// Streams a search course by course as tee sheets arrive, then a summary with every
+/// course's status. `closed` courses aren't fetched, only listed in the summary.
+pub fn search_updates(
+    fetch_log: FetchLog,
+    sheets: Vec<(GolfCourse, NaiveDate)>,
+    closed: Vec<CourseStatus>,
+    players: u32,
+    holes: Option<u32>,
+) -> impl Stream<Item = SearchUpdate> {
+    let (tx, rx) = tokio::sync::mpsc::channel(16);
+
+    tokio::spawn(async move {
+        let mut statuses = closed;
+        let mut total = 0;
+
+        let mut sheets = stream_tee_sheets(fetch_log, sheets, players, holes);
+        while let Some(sheet) = sheets.next().await {
+            let (state, reason, tee_times) = match sheet.result {
+                Ok(tee_times) => (CourseState::Open, None, tee_times),
+                Err(e) => (CourseState::Error, Some(e), Vec::new()),
+            };
+            let status = CourseStatus {
+                slug: sheet.course.slug,
+                course: sheet.course.name,
+                date: sheet.date,
+                status: state,
+                reason,
+            };
+
+            total += tee_times.len();
+            statuses.push(status.clone());
 
-pub async fn get_tee_times(
+            // The client went away, so stop fetching for it
+            if tx.send(SearchUpdate::Course(CourseResult { status, tee_times })).await.is_err() {
+                return;
+            }
+        }
+
+        statuses.sort_by(|a, b| (a.date, &a.course).cmp(&(b.date, &b.course)));
+        let _ = tx.send(SearchUpdate::Done(SearchSummary { tee_times: total, courses: statuses })).await;
+    });
+
+    stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|update| (update, rx)) })
+}
+
+/// Fetches each course's tee times for `date`, reusing tee sheets fetched within `TeeTimeCache::TTL`.
+/// Failed fetches aren't cached, so they are retried next time.
+pub async fn get_cached_tee_times(
+    fetch_log: &FetchLog,
+    cache: &TeeTimeCache,
     courses: &[&GolfCourse],
     date: &str,
     players: u32,
+    holes: Option<u32>,
 ) -> Vec<TeeTime> {
-    let mut results = Vec::new();
+    let client = Client::new();
+
+    let mut results: Vec<TeeTime> = Vec::new();
+    let mut missing: Vec<&GolfCourse> = Vec::new();
+    for course in courses {
+        match cache.get(&TeeTimeCache::key(&course.slug, date, players, holes)) {
+            Some(tee_times) => results.extend(tee_times),
+            None => missing.push(course),
+        }
+    }
+
+    let tasks = missing
+        .iter()
+        .map(|course| fetch_course(&client, course, date, players, holes));
 
-    results.extend(book_a_tee_time::search(courses, date, players).await);
-    results.extend(golfback::search(courses, date, players).await);
-    results.extend(foreup::search(courses, date, players).await);
-    results.extend(teeitup::search(courses, date).await); //, players).await);
-    // later:
-    // results.extend(cps::search(...).await);
+    for (course, result) in missing.iter().zip(join_all(tasks).await) {
+        if let Ok(mut tee_times) = result {
+            fetch_log.record(&course.slug);
+            tee_times.retain(|tt| fits_request(tt, players, holes));
+            cache.insert(TeeTimeCache::key(&course.slug, date, players, holes), tee_times.clone());
+            results.extend(tee_times);
+        }
+    }
 
     results
 }
 
+/// Tee times already on hand, without fetching: the cache for this search, else the
+/// watch poller's last poll re-priced for the party. Courses with neither add nothing.
+pub fn known_tee_times(
+    cache: &TeeTimeCache,
+    history: &TeeTimeHistory,
+    courses: &[&GolfCourse],
+    date: &str,
+    players: u32,
+    holes: Option<u32>,
+) -> Vec<TeeTime> {
+    let day = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok();
+
+    courses
+        .iter()
+        .flat_map(|course| {
+            cache
+                .get(&TeeTimeCache::key(&course.slug, date, players, holes))
+                .or_else(|| {
+                    let polled = history.sheet(&course.slug, day?)?;
+                    Some(polled
+                        .iter()
+                        .filter(|tt| fits_request(tt, players, holes))
+                        .map(|tt| for_party(tt, players))
+                        .collect())
+                })
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// One point per searched course with how many tee times it has, the earliest
+/// and the cheapest. Courses without any are kept so the map can show them.
+pub fn summarize_by_course(
+    courses: &[&GolfCourse],
+    statuses: &[CourseStatus],
+    tee_times: &[TeeTime],
+) -> FeatureCollection<CourseTeeTimes> {
+    let features = courses
+        .iter()
+        .map(|course| {
+            let status = statuses.iter().find(|s| s.slug == course.slug);
+            let at_course: Vec<&TeeTime> = tee_times.iter().filter(|tt| tt.slug == course.slug).collect();
+
+            Feature::point(course.lat, course.lon, CourseTeeTimes {
+                slug: course.slug.clone(),
+                course: course.name.clone(),
+                status: status.map_or(CourseState::Open, |s| s.status),
+                reason: status.and_then(|s| s.reason.clone()),
+                tee_times: at_course.len(),
+                earliest_tee_time: at_course.iter().map(|tt| tt.tee_time).min(),
+                cheapest_price_per_player: at_course
+                    .iter()
+                    .map(|tt| tt.price_per_player)
+                    .min_by(|a, b| a.total_cmp(b)),
+            })
+        })
+        .collect();
+
+    FeatureCollection::new(features)
+}
+
+/// One point per cluster at its courses' average position, with how many tee
+/// times its courses have between them and the cheapest.
+pub fn summarize_clusters(clusters: &[Vec<&GolfCourse>], tee_times: &[TeeTime]) -> FeatureCollection<Cluster> {
+    let mut by_slug: HashMap<&str, Vec<&TeeTime>> = HashMap::new();
+    for tee_time in tee_times {
+        by_slug.entry(tee_time.slug.as_str()).or_default().push(tee_time);
+    }
+
+    let features = clusters
+        .iter()
+        .map(|courses| {
+            let in_cluster: Vec<&TeeTime> = courses
+                .iter()
+                .flat_map(|c| by_slug.get(c.slug.as_str()).into_iter().flatten().copied())
+                .collect();
+
+            let n = courses.len() as f64;
+            let lat = courses.iter().map(|c| c.lat).sum::<f64>() / n;
+            let lon = courses.iter().map(|c| c.lon).sum::<f64>() / n;
+
+            Feature::point(lat, lon, Cluster {
+                courses: courses.len(),
+                slugs: courses.iter().map(|c| c.slug.clone()).collect(),
+                tee_times: in_cluster.len(),
+                cheapest_price_per_player: in_cluster
+                    .iter()
+                    .map(|tt| tt.price_per_player)
+                    .min_by(|a, b| a.total_cmp(b)),
+            })
+        })
+        .collect();
+
+    FeatureCollection::new(features)
+}
+
+pub mod csv {
+    use super::*;
+
+    pub const HEADER: &str =
+        "course,date,time,price_per_player,total_for_party,players,holes,distance_km,book_url\n";
+
+    /// One spreadsheet row, with the tee time in the course's local time.
+    pub fn row(tee_time: &TeeTime, distance_km: Option<f64>) -> String {
+        let local = tee_time.tee_time.with_timezone(&tee_time.timezone);
+
+        let fields = [
+            field(&tee_time.course),
+            local.format("%Y-%m-%d").to_string(),
+            local.format("%H:%M").to_string(),
+            format!("{:.2}", tee_time.price_per_player),
+            format!("{:.2}", tee_time.total_for_party),
+            tee_time.players.to_string(),
+            tee_time.holes.map(|h| h.to_string()).unwrap_or_default(),
+            distance_km.map(|d| format!("{:.1}", d)).unwrap_or_default(),
+            field(&tee_time.book_url),
+        ];
+
+        fields.join(",") + "\n"
+    }
+
+    /// Quotes a field when it holds a comma, quote or line break.
+    fn field(value: &str) -> String {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+}
+
+pub mod ics {
+    use super::*;
+
+    /// A VCALENDAR with one event per tee time, for calendar apps to subscribe to.
+    pub fn calendar(name: &str, tee_times: &[TeeTime]) -> String {
+        let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            "PRODID:-//tee_time_tracker_v2//Tee Times//EN".to_string(),
+            "CALSCALE:GREGORIAN".to_string(),
+            "METHOD:PUBLISH".to_string(),
+            format!("X-WR-CALNAME:{}", text(name)),
+        ];
+
+        for tee_time in tee_times {
+            let start = tee_time.tee_time;
+            let end = start + round_length(tee_time.holes);
+
+            lines.extend([
+                "BEGIN:VEVENT".to_string(),
+                format!("UID:{}-{}@tee-time-tracker", tee_time.slug, start.format("%Y%m%dT%H%M%SZ")),
+                format!("DTSTAMP:{}", stamp),
+                format!("DTSTART:{}", start.format("%Y%m%dT%H%M%SZ")),
+                format!("DTEND:{}", end.format("%Y%m%dT%H%M%SZ")),
+                format!("SUMMARY:{}", text(&format!(
+                    "Tee time at {} ({} open, ${:.2} each)",
+                    tee_time.course, tee_time.players, tee_time.price_per_player
+                ))),
+                format!("LOCATION:{}", text(&tee_time.course)),
+                format!("GEO:{:.6};{:.6}", tee_time.lat, tee_time.lon),
+                format!("DESCRIPTION:{}", text(&format!(
+                    "{} open spots{}, ${:.2} per player.\nBook: {}",
+                    tee_time.players,
+                    tee_time.holes.map(|h| format!(" for {} holes", h)).unwrap_or_default(),
+                    tee_time.price_per_player,
+                    tee_time.book_url
+                ))),
+                format!("URL:{}", tee_time.book_url),
+                "TRANSP:TRANSPARENT".to_string(),
+                "END:VEVENT".to_string(),
+            ]);
+        }
+
+        lines.push("END:VCALENDAR".to_string());
+
+        lines.iter().map(|line| fold(line)).collect()
+    }
+
+    /// Roughly how long the round takes, so the event blocks out a sensible slot.
+    fn round_length(holes: Option<u32>) -> chrono::Duration {
+        match holes {
+            Some(h) if h <= 9 => chrono::Duration::minutes(135),
+            _ => chrono::Duration::minutes(270),
+        }
+    }
+
+    /// Escapes a TEXT value (RFC 5545 3.3.11).
+    fn text(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace(';', "\\;")
+            .replace(',', "\\,")
+            .replace('\n', "\\n")
+    }
+
+    /// Ends a content line with CRLF, folding it every 75 octets (RFC 5545 3.1).
+    fn fold(line: &str) -> String {
+        let mut folded = String::new();
+        let mut width = 0;
+
+        for c in line.chars() {
+            if width + c.len_utf8() > 75 {
+                folded.push_str("\r\n ");
+                width = 1;
+            }
+            folded.push(c);
+            width += c.len_utf8();
+        }
+
+        folded + "\r\n"
+    }
+}
+
+pub mod atom {
+    use super::*;
+
+    /// Tag URI prefix for feed and entry ids.
+    const TAG: &str = "tag:tee-time-tracker,2025:";
+
+    /// An Atom feed with one entry per newly opened tee time. Entry ids come from
+    /// `TeeTime::entry_id`, so a reader sees each slot once however often it polls.
+    pub fn feed(feed_id: &str, title: &str, changes: &[TeeTimeChange]) -> String {
+        let updated = changes.iter().map(|c| c.seen).max().unwrap_or_else(Utc::now);
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+        xml.push_str(&format!("  <id>{}{}</id>\n", TAG, escape(feed_id)));
+        xml.push_str(&format!("  <title>{}</title>\n", escape(title)));
+        xml.push_str(&format!("  <updated>{}</updated>\n", updated.to_rfc3339()));
+        xml.push_str("  <author><name>tee_time_tracker_v2</name></author>\n");
+
+        for change in changes {
+            let tee_time = &change.tee_time;
+            let local = tee_time.tee_time.with_timezone(&tee_time.timezone);
+
+            xml.push_str("  <entry>\n");
+            xml.push_str(&format!("    <id>{}{}</id>\n", TAG, escape(&change.id)));
+            xml.push_str(&format!(
+                "    <title>{} {} - {} open, ${:.2}</title>\n",
+                escape(&tee_time.course),
+                local.format("%a %b %-d %-I:%M %p"),
+                tee_time.players,
+                tee_time.price_per_player
+            ));
+            xml.push_str(&format!("    <updated>{}</updated>\n", change.seen.to_rfc3339()));
+            xml.push_str(&format!("    <link rel=\"alternate\" href=\"{}\"/>\n", escape(&tee_time.book_url)));
+            xml.push_str(&format!(
+                "    <summary>{} open spots{} at {} on {}, ${:.2} per player.</summary>\n",
+                tee_time.players,
+                tee_time.holes.map(|h| format!(" for {} holes", h)).unwrap_or_default(),
+                escape(&tee_time.course),
+                local.format("%A, %B %-d at %-I:%M %p"),
+                tee_time.price_per_player
+            ));
+            xml.push_str("  </entry>\n");
+        }
+
+        xml.push_str("</feed>\n");
+        xml
+    }
+
+    fn escape(value: &str) -> String {
+        value
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn course(source: &str, id: serde_json::Value) -> GolfCourse {
+        let mut course: GolfCourse = serde_json::from_value(serde_json::json!({
+            "name": "Test Course",
+            "lat": 39.1,
+            "lon": -94.6,
+            "source": source,
+            "id": id,
+        }))
+        .expect("test course is valid");
+        course.slug = "test-course".to_string();
+
+        course
+    }
+
+    fn teeitup_id() -> TeeItUpCourseId {
+        TeeItUpCourseId {
+            id: 9,
+            url: "https://test-course.book.teeitup.golf".to_string(),
+            alias: "test-course".to_string(),
+        }
+    }
+
+    fn prices(tee_times: &[TeeTime]) -> Vec<(f64, f64)> {
+        tee_times.iter().map(|tt| (tt.price_per_player, tt.total_for_party)).collect()
+    }
+
+    #[test]
+    fn bookateetime_prices_are_per_golfer() {
+        let course = course("bookateetime", serde_json::json!("118-1"));
+        let body = include_str!("../tests/fixtures/bookateetime_search_4.html");
+
+        let tee_times = book_a_tee_time::parse(&course, body, 4);
+
+        assert_eq!(prices(&tee_times), vec![(38.0, 152.0), (27.5, 110.0)]);
+        assert_eq!(tee_times[0].tee_time.to_rfc3339(), "2026-10-20T12:30:00+00:00");
+        assert_eq!(tee_times[0].holes, Some(18));
+        assert!(tee_times[0].book_url.ends_with("/book/118-1/202610200730?selectedPlayers=4&selectedHoles=18"));
+    }
+
+    #[test]
+    fn golfback_prices_are_per_player() {
+        let course = course("golfback", serde_json::json!("857a12d4-a9cf-4a43-afe2-60940bdc7438"));
+        let body = include_str!("../tests/fixtures/golfback_teetimes.json");
+
+        let tee_times = golfback::parse(&course, "857a12d4-a9cf-4a43-afe2-60940bdc7438", "2026-10-20", body, 3, None)
+            .expect("fixture parses");
+
+        assert_eq!(prices(&tee_times), vec![(34.0, 102.0), (29.99, 89.97)]);
+        assert_eq!(tee_times[0].rate.as_deref(), Some("0bc2bf83-2bf5-4ba1-be8b-a06691bf761a"));
+        assert_eq!(tee_times[1].players, 2);
+    }
+
+    #[test]
+    fn golfback_uses_the_rate_for_the_requested_holes() {
+        let course = course("golfback", serde_json::json!("857a12d4-a9cf-4a43-afe2-60940bdc7438"));
+        let body = include_str!("../tests/fixtures/golfback_teetimes.json");
+
+        let tee_times = golfback::parse(&course, "857a12d4-a9cf-4a43-afe2-60940bdc7438", "2026-10-20", body, 2, Some(9))
+            .expect("fixture parses");
+
+        // The twilight tee time has no 9-hole rate, so it's left out
+        assert_eq!(tee_times.len(), 1);
+        assert_eq!(prices(&tee_times), vec![(21.5, 43.0)]);
+        assert_eq!(tee_times[0].holes, Some(9));
+        assert!(tee_times[0].book_url.contains("rateId=5d1f9a70-8c3e-4b8e-a2f4-7e6b1c9d0a11&holes=9&players=2"));
+    }
+
+    fn tee_time(players: u32, holes: Option<u32>) -> TeeTime {
+        TeeTime {
+            course: "Test Course".to_string(),
+            slug: "test-course".to_string(),
+            tee_time: Utc::now(),
+            timezone: GolfCourse::DEFAULT_TIMEZONE,
+            price_per_player: 30.0,
+            total_for_party: 30.0,
+            players,
+            holes,
+            rate: None,
+            lat: 39.1,
+            lon: -94.6,
+            book_url: "https://example.com/book".to_string(),
+        }
+    }
+
+    #[test]
+    fn fits_request_needs_room_for_the_party() {
+        assert!(fits_request(&tee_time(4, Some(18)), 4, None));
+        assert!(fits_request(&tee_time(4, Some(18)), 1, None));
+        assert!(!fits_request(&tee_time(3, Some(18)), 4, None));
+    }
+
+    #[test]
+    fn fits_request_matches_the_round_length() {
+        assert!(fits_request(&tee_time(4, Some(9)), 2, Some(9)));
+        assert!(!fits_request(&tee_time(4, Some(18)), 2, Some(9)));
+        assert!(fits_request(&tee_time(4, Some(18)), 2, None));
+        // A provider that doesn't say is given the benefit of the doubt
+        assert!(fits_request(&tee_time(4, None), 2, Some(18)));
+    }
+
+    #[test]
+    fn check_holes_takes_only_round_lengths() {
+        assert_eq!(check_holes(None), Ok(None));
+        assert_eq!(check_holes(Some(9)), Ok(Some(9)));
+        assert_eq!(check_holes(Some(18)), Ok(Some(18)));
+        assert!(check_holes(Some(12)).is_err());
+    }
+
+    #[test]
+    fn foreup_green_and_cart_fees_are_per_player() {
+        let course = course(
+            "foreup",
+            serde_json::json!({ "schedule_id": 7829, "facility_id": 19765, "booking_class": 12345 }),
+        );
+        let body = include_str!("../tests/fixtures/foreup_times.json");
+
+        let tee_times = foreup::parse(&course, "https://foreupsoftware.com/index.php/booking/19765/7829", body, 2)
+            .expect("fixture parses");
+
+        assert_eq!(prices(&tee_times), vec![(49.75, 99.5), (21.0, 42.0)]);
+        assert_eq!(tee_times[0].tee_time.to_rfc3339(), "2026-10-20T12:30:00+00:00");
+        assert_eq!(tee_times[1].holes, Some(18));
+    }
+
+    #[test]
+    fn teeitup_cents_are_per_golfer() {
+        let course = course(
+            "teeitup",
+            serde_json::json!({ "id": 9, "url": "https://test-course.book.teeitup.golf", "alias": "test-course" }),
+        );
+        let body = include_str!("../tests/fixtures/teeitup_teetimes.json");
+
+        let tee_times = teeitup::parse(&course, &teeitup_id(), "2026-10-20", body, 4, Some(18))
+            .expect("fixture parses");
+
+        // The second tee time's promotion overrides its rate
+        assert_eq!(prices(&tee_times), vec![(54.0, 216.0), (35.2, 140.8)]);
+        assert_eq!(tee_times[0].rate.as_deref(), Some("18 Holes Riding"));
+    }
+
+    #[test]
+    fn clusters_count_their_own_courses_tee_times() {
+        let mut other = course("cps", serde_json::json!("other"));
+        other.slug = "other-course".to_string();
+        let test = course("cps", serde_json::json!("test"));
+        let cheap = TeeTime { price_per_player: 22.5, ..tee_time(4, Some(18)) };
+        let elsewhere = TeeTime { slug: "other-course".to_string(), price_per_player: 10.0, ..tee_time(4, Some(18)) };
+
+        let clusters = summarize_clusters(
+            &[vec![&test], vec![&other, &test]],
+            &[tee_time(4, Some(18)), cheap, elsewhere],
+        );
+        let counts: Vec<(usize, Option<f64>)> = clusters
+            .features
+            .iter()
+            .map(|f| (f.properties.tee_times, f.properties.cheapest_price_per_player))
+            .collect();
+
+        assert_eq!(counts, [(2, Some(22.5)), (3, Some(10.0))]);
+    }
+
+    #[test]
+    fn csv_rows_use_the_course_time_zone() {
+        let tee_time = TeeTime {
+            tee_time: "2026-10-20T14:30:00Z".parse().unwrap(),
+            timezone: chrono_tz::America::Denver,
+            ..tee_time(4, Some(18))
+        };
+
+        assert_eq!(
+            csv::row(&tee_time, Some(12.34)),
+            "Test Course,2026-10-20,08:30,30.00,30.00,4,18,12.3,https://example.com/book\n",
+        );
+    }
+
+    type Priced = (DateTime<Utc>, f64, f64, String);
+
+    /// A single-golfer fetch narrowed and re-totaled for `players` the way batch searches
+    /// do it, next to a fetch made directly for `players`.
+    fn shared_and_direct(single: Vec<TeeTime>, direct: Vec<TeeTime>, players: u32, holes: Option<u32>) -> (Vec<Priced>, Vec<Priced>) {
+        let priced = |tt: &TeeTime| (tt.tee_time, tt.price_per_player, tt.total_for_party, tt.book_url.clone());
+
+        let shared = single
+            .iter()
+            .filter(|tt| fits_request(tt, players, holes))
+            .map(|tt| priced(&for_party(tt, players)))
+            .collect();
+        let direct = direct
+            .iter()
+            .filter(|tt| fits_request(tt, players, holes))
+            .map(priced)
+            .collect();
+
+        (shared, direct)
+    }
+
+    /// A provider's parser over its fixture, and the searches to check it with.
+    struct PartyCase {
+        provider: &'static str,
+        /// The fixture tee sheet as fetched for `players` and `holes`.
+        parse: Box<dyn Fn(u32, Option<u32>) -> Vec<TeeTime>>,
+        parties: &'static [u32],
+        round_lengths: &'static [Option<u32>],
+        /// How many tee times the party gets, where the fixture pins it down.
+        expected: Option<usize>,
+    }
+
+    #[test]
+    fn for_party_matches_a_direct_fetch() {
+        let bookateetime = course("bookateetime", serde_json::json!("118-1"));
+        let golfback = course("golfback", serde_json::json!("857a12d4-a9cf-4a43-afe2-60940bdc7438"));
+        let foreup = course(
+            "foreup",
+            serde_json::json!({ "schedule_id": 7829, "facility_id": 19765, "booking_class": 12345 }),
+        );
+        let teeitup = course(
+            "teeitup",
+            serde_json::json!({ "id": 9, "url": "https://test-course.book.teeitup.golf", "alias": "test-course" }),
+        );
+
+        let cases = vec![
+            PartyCase {
+                provider: "bookateetime",
+                parse: Box::new(move |players, _| {
+                    let html = match players {
+                        1 => include_str!("../tests/fixtures/bookateetime_search_1.html"),
+                        _ => include_str!("../tests/fixtures/bookateetime_search_4.html"),
+                    };
+                    book_a_tee_time::parse(&bookateetime, html, players)
+                }),
+                parties: &[4],
+                round_lengths: &[None],
+                expected: Some(1),
+            },
+            PartyCase {
+                provider: "golfback",
+                parse: Box::new(move |players, holes| {
+                    let body = include_str!("../tests/fixtures/golfback_teetimes.json");
+                    golfback::parse(&golfback, "857a12d4-a9cf-4a43-afe2-60940bdc7438", "2026-10-20", body, players, holes)
+                        .expect("fixture parses")
+                }),
+                parties: &[2, 3, 4],
+                round_lengths: &[None, Some(9), Some(18)],
+                expected: None,
+            },
+            PartyCase {
+                provider: "foreup",
+                parse: Box::new(move |players, _| {
+                    let body = include_str!("../tests/fixtures/foreup_times.json");
+                    foreup::parse(&foreup, "https://foreupsoftware.com/index.php/booking/19765/7829", body, players)
+                        .expect("fixture parses")
+                }),
+                parties: &[2, 3, 4],
+                round_lengths: &[None],
+                expected: None,
+            },
+            PartyCase {
+                provider: "teeitup",
+                parse: Box::new(move |players, holes| {
+                    let body = include_str!("../tests/fixtures/teeitup_teetimes.json");
+                    teeitup::parse(&teeitup, &teeitup_id(), "2026-10-20", body, players, holes)
+                        .expect("fixture parses")
+                }),
+                parties: &[2, 3, 4],
+                round_lengths: &[Some(18)],
+                expected: Some(2),
+            },
+        ];
+
+        for PartyCase { provider, parse, parties, round_lengths, expected } in cases {
+            for &holes in round_lengths {
+                for &players in parties {
+                    let (shared, direct) = shared_and_direct(parse(1, holes), parse(players, holes), players, holes);
+
+                    assert!(!shared.is_empty(), "{}: {} players, {:?} holes", provider, players, holes);
+                    if let Some(expected) = expected {
+                        assert_eq!(shared.len(), expected, "{}: {} players, {:?} holes", provider, players, holes);
+                    }
+                    assert_eq!(shared, direct, "{}: {} players, {:?} holes", provider, players, holes);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,402 @@
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+
+use axum::{
+    body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware,
+    response::{sse::{Event, Sse}, IntoResponse, Json, Response},
+    routing::{get, post},
+    Router,
+};
+use futures::stream::Stream;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use crate::structs::{
+    AppState,
+    ApiError,
+    Area,
+    BatchResult,
+    BatchSearch,
+    ChangeKind,
+    ClientMessage,
+    Cluster,
+    ClusterQuery,
+    CourseResult,
+    CourseStatus,
+    CourseTeeTimeQuery,
+    CourseTeeTimes,
+    CourseQuery,
+    FeatureCollection,
+    FeedQuery,
+    GolfCourse,
+    NdJsonLine,
+    SearchSummary,
+    ServerMessage,
+    Subscription,
+    TeeTime,
+    TeeTimeChange,
+    TeeTimeQuery,
+    TeeTimeSearch,
+};
+
+/// Largest error body rewritten as JSON; anything bigger isn't a rejection message.
+const MAX_ERROR_BODY: usize = 64 * 1024;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Tee Time Tracker",
+        description = "Golf courses and their tee times across booking providers. \
+            The unversioned paths (`/courses`, `/tee_times`, ...) are deprecated aliases of these.",
+    ),
+    servers((url = "/v1")),
+    paths(
+        courses,
+        courses_in_area,
+        clusters,
+        crate::course_handler,
+        course_tee_times,
+        course_feed,
+        tee_times,
+        tee_times_in_area,
+        batch,
+        tee_times_stream,
+        tee_times_calendar,
+        crate::ws_handler,
+        crate::list_watches_handler,
+        crate::create_watch_handler,
+        crate::watch_handler,
+        crate::delete_watch_handler,
+        crate::watch_ics_handler,
+        crate::watch_atom_handler,
+        crate::reload_handler,
+        crate::add_course_handler,
+        crate::update_course_handler,
+        crate::remove_course_handler,
+    ),
+    components(schemas(
+        TeeTimeSearch,
+        CourseStatus,
+        CourseResult,
+        SearchSummary,
+        ClientMessage,
+        Subscription,
+        ServerMessage,
+        TeeTimeChange,
+        ChangeKind,
+    )),
+    modifiers(&AdminToken),
+    tags(
+        (name = "courses", description = "The course catalog"),
+        (name = "tee_times", description = "Tee time searches, live and exported"),
+        (name = "watches", description = "Saved searches and their feeds"),
+        (name = "admin", description = "Catalog editing; needs `ADMIN_TOKEN`"),
+    ),
+)]
+pub struct ApiDoc;
+
+/// `Authorization: Bearer $ADMIN_TOKEN` for the admin paths.
+struct AdminToken;
+
+impl Modify for AdminToken {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "admin_token",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+/// Everything under `/v1`. Errors, including axum's own rejections, are `ApiError` JSON.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/openapi.json", get(openapi))
+        .route("/courses", get(courses).post(courses_in_area))
+        .route("/courses/clusters", get(clusters))
+        .route("/courses/:slug", get(crate::course_handler))
+        .route("/courses/:slug/tee_times", get(course_tee_times))
+        .route("/courses/:slug/feed.atom", get(course_feed))
+        .route("/tee_times", get(tee_times).post(tee_times_in_area))
+        .route("/tee_times/batch", post(batch))
+        .route("/tee_times/stream", get(tee_times_stream))
+        .route("/tee_times.ics", get(tee_times_calendar))
+        .route("/ws", get(crate::ws_handler))
+        .route("/watches", crate::watches_routes())
+        .route("/watches/:id", get(crate::watch_handler).delete(crate::delete_watch_handler))
+        .route("/watches/:id/feed.ics", get(crate::watch_ics_handler))
+        .route("/watches/:id/feed.atom", get(crate::watch_atom_handler))
+        .nest("/admin", crate::admin_routes())
+        .fallback(not_found)
+        .layer(middleware::map_response(json_errors))
+}
+
+async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+async fn not_found() -> ApiError {
+    ApiError::new(StatusCode::NOT_FOUND, "No such endpoint")
+}
+
+/// Rewrites plain-text error responses, e.g. axum's query and JSON body rejections,
+/// as `ApiError` bodies so every error, on `/v1` and the old paths, has the same shape.
+pub(crate) async fn json_errors(response: Response) -> Response {
+    let status = response.status();
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if !(status.is_client_error() || status.is_server_error()) || is_json {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let text = body::to_bytes(body, MAX_ERROR_BODY).await.unwrap_or_default();
+    let text = String::from_utf8_lossy(&text);
+    let message = match text.trim() {
+        "" => status.canonical_reason().unwrap_or("Request failed"),
+        text => text,
+    };
+
+    let mut rewritten = ApiError::new(status, message).into_response();
+    // e.g. `Allow` on 405s
+    if let Some(allow) = parts.headers.get(header::ALLOW) {
+        rewritten.headers_mut().insert(header::ALLOW, allow.clone());
+    }
+
+    rewritten
+}
+
+#[utoipa::path(
+    get,
+    path = "/courses",
+    tag = "courses",
+    params(CourseQuery),
+    responses(
+        (status = 200, description = "Matching courses, best name matches or nearest first", content(
+            (Vec<GolfCourse> = "application/json"),
+            (FeatureCollection<GolfCourse> = "application/geo+json"),
+        )),
+        (status = 400, description = "Invalid query", body = ApiError),
+    ),
+)]
+async fn courses(
+    State(state): State<AppState>,
+    Query(query): Query<CourseQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    crate::list_courses(&state, &query, &headers)
+}
+
+
+/// Same as `GET /courses`, with the search area as the body.
+#[utoipa::path(
+    post,
+    path = "/courses",
+    tag = "courses",
+    params(CourseQuery),
+    request_body = Area,
+    responses(
+        (status = 200, description = "Courses inside the area", content(
+            (Vec<GolfCourse> = "application/json"),
+            (FeatureCollection<GolfCourse> = "application/geo+json"),
+        )),
+        (status = 400, description = "Invalid query or area", body = ApiError),
+    ),
+)]
+async fn courses_in_area(
+    State(state): State<AppState>,
+    Query(query): Query<CourseQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Response, ApiError> {
+    crate::courses_area_handler(State(state), Query(query), headers, body).await
+}
+
+
+/// Courses grouped for a zoomed-out map, with each cluster's tee time count and cheapest price.
+/// Below zoom 10 the counts only cover tee sheets already cached or polled for watches.
+#[utoipa::path(
+    get,
+    path = "/courses/clusters",
+    tag = "courses",
+    params(ClusterQuery),
+    responses(
+        (status = 200, description = "One point per cluster", body = FeatureCollection<Cluster>, content_type = "application/geo+json"),
+        (status = 400, description = "Invalid query", body = ApiError),
+    ),
+)]
+async fn clusters(
+    State(state): State<AppState>,
+    Query(query): Query<ClusterQuery>,
+) -> Result<FeatureCollection<Cluster>, ApiError> {
+    crate::clusters_handler(State(state), Query(query)).await
+}
+
+
+#[utoipa::path(
+    get,
+    path = "/courses/{slug}/tee_times",
+    tag = "tee_times",
+    params(("slug" = String, Path), CourseTeeTimeQuery),
+    responses(
+        (status = 200, description = "The course's tee times", content(
+            (Vec<TeeTime> = "application/json"),
+            (FeatureCollection<CourseTeeTimes> = "application/geo+json"),
+            (String = "text/csv"),
+            (NdJsonLine = "application/x-ndjson"),
+        )),
+        (status = 400, description = "Invalid query", body = ApiError),
+        (status = 404, description = "No such course", body = ApiError),
+    ),
+)]
+async fn course_tee_times(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Query(query): Query<CourseTeeTimeQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    crate::course_tee_times_handler(State(state), Path(slug), Query(query), headers).await
+}
+
+
+/// Tee times that opened up at one course since earlier polls.
+#[utoipa::path(
+    get,
+    path = "/courses/{slug}/feed.atom",
+    tag = "courses",
+    params(("slug" = String, Path), FeedQuery),
+    responses(
+        (status = 200, description = "Atom feed, newest first", body = String, content_type = "application/atom+xml"),
+        (status = 404, description = "No such course", body = ApiError),
+    ),
+)]
+async fn course_feed(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+    Query(query): Query<FeedQuery>,
+) -> Result<Response, ApiError> {
+    crate::course_atom_handler(State(state), Path(slug), Query(query)).await
+}
+
+
+/// Tee times at the matching courses. `csv` and `ndjson` stream as each course answers;
+/// `ndjson` lines are tagged by `type`, with a `course_status` line for each course
+/// whose tee sheet couldn't be fetched.
+#[utoipa::path(
+    get,
+    path = "/tee_times",
+    tag = "tee_times",
+    params(TeeTimeQuery),
+    responses(
+        (status = 200, description = "Tee times, or a `TeeTimeSearch` with `include_status=true`", content(
+            (Vec<TeeTime> = "application/json"),
+            (FeatureCollection<CourseTeeTimes> = "application/geo+json"),
+            (String = "text/csv"),
+            (NdJsonLine = "application/x-ndjson"),
+        )),
+        (status = 400, description = "Invalid query", body = ApiError),
+    ),
+)]
+async fn tee_times(
+    State(state): State<AppState>,
+    Query(query): Query<TeeTimeQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    crate::tee_times_handler(State(state), Query(query), headers).await
+}
+
+
+/// Same as `GET /tee_times`, with the search area as the body.
+#[utoipa::path(
+    post,
+    path = "/tee_times",
+    tag = "tee_times",
+    params(TeeTimeQuery),
+    request_body = Area,
+    responses(
+        (status = 200, description = "Tee times at courses inside the area", content(
+            (Vec<TeeTime> = "application/json"),
+            (FeatureCollection<CourseTeeTimes> = "application/geo+json"),
+            (String = "text/csv"),
+            (NdJsonLine = "application/x-ndjson"),
+        )),
+        (status = 400, description = "Invalid query or area", body = ApiError),
+    ),
+)]
+async fn tee_times_in_area(
+    State(state): State<AppState>,
+    Query(query): Query<TeeTimeQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Response, ApiError> {
+    crate::tee_times_area_handler(State(state), Query(query), headers, body).await
+}
+
+
+/// Several tee time searches at once, keyed by `id` or position. Tee sheets that
+/// more than one search needs are fetched once. A search that doesn't parse gets
+/// its error under its key, like one that fails to run.
+#[utoipa::path(
+    post,
+    path = "/tee_times/batch",
+    tag = "tee_times",
+    request_body = Vec<BatchSearch>,
+    responses(
+        (status = 200, description = "Each search's result or error, by key", body = HashMap<String, BatchResult>),
+        (status = 400, description = "Empty or oversized batch, a repeated id, or a body that isn't JSON", body = ApiError),
+        (status = 422, description = "Body isn't a JSON array", body = ApiError),
+    ),
+)]
+async fn batch(
+    State(state): State<AppState>,
+    Json(specs): Json<Vec<serde_json::Value>>,
+) -> Result<Json<BTreeMap<String, BatchResult>>, ApiError> {
+    crate::batch_handler(State(state), Json(specs)).await
+}
+
+
+/// The same search as `GET /tee_times` as Server-Sent Events: a `course` event
+/// (`CourseResult`) per course and day as its provider answers, then one `summary`
+/// event (`SearchSummary`).
+#[utoipa::path(
+    get,
+    path = "/tee_times/stream",
+    tag = "tee_times",
+    params(TeeTimeQuery),
+    responses(
+        (status = 200, description = "Event stream", body = String, content_type = "text/event-stream"),
+        (status = 400, description = "Invalid query", body = ApiError),
+    ),
+)]
+async fn tee_times_stream(
+    State(state): State<AppState>,
+    Query(query): Query<TeeTimeQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    crate::tee_times_stream_handler(State(state), Query(query)).await
+}
+
+
+/// The same search as `GET /tee_times` as a calendar to subscribe to, a week from `date`
+/// unless `end_date` says otherwise.
+#[utoipa::path(
+    get,
+    path = "/tee_times.ics",
+    tag = "tee_times",
+    params(TeeTimeQuery),
+    responses(
+        (status = 200, description = "iCalendar events, soonest first", body = String, content_type = "text/calendar"),
+        (status = 400, description = "Invalid query", body = ApiError),
+    ),
+)]
+async fn tee_times_calendar(
+    State(state): State<AppState>,
+    Query(query): Query<TeeTimeQuery>,
+) -> Result<Response, ApiError> {
+    crate::tee_times_ics_handler(State(state), Query(query)).await
+}
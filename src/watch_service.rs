@@ -0,0 +1,327 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use chrono::{Local, NaiveDate, Utc};
+use futures::stream::StreamExt;
+
+use crate::course_service::courses::open_sheets;
+use crate::tee_time_service::{check_holes, fits_request, for_party, stream_tee_sheets};
+
+use crate::structs::{
+    AppState,
+    ChangeKind,
+    GolfCourse,
+    TeeTime,
+    TeeTimeChange,
+};
+
+pub mod poller {
+    pub use super::*;
+
+    /// Seconds between polls; `0` turns the poller off.
+    pub const POLL_ENV: &str = "WATCH_POLL_SECS";
+    /// Off unless asked for: every poll fetches a week of tee sheets for every course,
+    /// which is a lot of traffic to put on the providers. Feeds and `/ws` stay empty
+    /// while it is off.
+    pub const DEFAULT_POLL_SECS: u64 = 0;
+
+    /// Days ahead, from today, that every poll covers.
+    pub const POLL_DAYS: u64 = 7;
+
+    /// Re-fetches every course's upcoming tee sheets on a timer and records what changed.
+    pub async fn poll_tee_times(state: AppState, every: Duration) {
+        let mut ticker = tokio::time::interval(every);
+
+        loop {
+            ticker.tick().await;
+
+            let changes = poll_once(&state).await;
+            if !changes.is_empty() {
+                println!("Tee time poll found {} change(s)", changes.len());
+            }
+        }
+    }
+
+    /// One pass over all courses. Fetches for a single player so every open slot is
+    /// seen; feeds narrow it down to each search's party size afterwards.
+    pub async fn poll_once(state: &AppState) -> Vec<TeeTimeChange> {
+        let today = Local::now().date_naive();
+        let dates: Vec<NaiveDate> = today.iter_days().take(POLL_DAYS as usize).collect();
+
+        let catalog = state.courses();
+        let courses: Vec<&GolfCourse> = catalog
+            .courses
+            .iter()
+            .filter(|course| course.provider.is_supported())
+            .collect();
+
+        let mut sheets = stream_tee_sheets(state.fetch_log.clone(), open_sheets(&courses, &dates), 1, None);
+
+        let mut changes = Vec::new();
+        while let Some(sheet) = sheets.next().await {
+            // A failed fetch says nothing about what was booked, so the last snapshot stands
+            let Ok(tee_times) = sheet.result else { continue };
+
+            let current: HashMap<String, TeeTime> = tee_times
+                .into_iter()
+                .map(|tt| (tt.entry_id(), tt))
+                .collect();
+
+            // The first poll of a tee sheet is the baseline, not a flood of new tee times
+            if let Some(previous) = state.history.replace_sheet(&sheet.course.slug, sheet.date, current.clone()) {
+                // Recorded per tee sheet so live subscribers hear about it straight away
+                let sheet_changes = diff(&previous, &current);
+                state.history.record(sheet_changes.clone());
+                changes.extend(sheet_changes);
+            }
+        }
+
+        state.history.prune(today);
+
+        changes
+    }
+
+    /// Tee times opened, booked or repriced between two polls of a tee sheet, earliest first.
+    /// Slots that dropped off because their start time passed aren't counted as booked.
+    pub fn diff(previous: &HashMap<String, TeeTime>, current: &HashMap<String, TeeTime>) -> Vec<TeeTimeChange> {
+        let seen = Utc::now();
+        let change = |id: &String, kind, tee_time: &TeeTime, previous_price_per_player| TeeTimeChange {
+            id: id.clone(),
+            kind,
+            seen,
+            tee_time: tee_time.clone(),
+            previous_price_per_player,
+        };
+
+        let mut changes: Vec<TeeTimeChange> = Vec::new();
+        for (id, tee_time) in current {
+            match previous.get(id) {
+                None => changes.push(change(id, ChangeKind::Opened, tee_time, None)),
+                Some(before) if before.price_per_player != tee_time.price_per_player => changes.push(
+                    change(id, ChangeKind::PriceChanged, tee_time, Some(before.price_per_player)),
+                ),
+                Some(_) => {}
+            }
+        }
+        for (id, tee_time) in previous {
+            if !current.contains_key(id) && tee_time.tee_time > seen {
+                changes.push(change(id, ChangeKind::Booked, tee_time, None));
+            }
+        }
+        changes.sort_by_key(|change| change.tee_time.tee_time);
+
+        changes
+    }
+}
+
+pub mod feeds {
+    pub use super::*;
+
+    /// Most entries a feed shows.
+    pub const MAX_ENTRIES: usize = 50;
+
+    /// What a saved search or course feed is narrowed to.
+    pub struct FeedFilter {
+        pub slugs: HashSet<String>,
+        pub players: u32,
+        pub holes: Option<u32>,
+        /// Local dates to keep, or any day the poller covers.
+        pub dates: Option<Vec<NaiveDate>>,
+    }
+
+    impl FeedFilter {
+        pub fn matches(&self, tee_time: &TeeTime) -> bool {
+            let local = tee_time.tee_time.with_timezone(&tee_time.timezone).date_naive();
+
+            self.slugs.contains(&tee_time.slug)
+                && fits_request(tee_time, self.players, self.holes)
+                && self.dates.as_ref().is_none_or(|dates| dates.contains(&local))
+        }
+
+        /// A polled tee time, fetched for one golfer, priced and linked for the party.
+        pub fn for_party(&self, tee_time: &TeeTime) -> TeeTime {
+            for_party(tee_time, self.players)
+        }
+    }
+
+    /// Newly opened tee times matching `filter` that haven't started yet, newest first,
+    /// priced for its party.
+    pub fn opened(changes: Vec<TeeTimeChange>, filter: &FeedFilter) -> Vec<TeeTimeChange> {
+        let now = Utc::now();
+
+        changes
+            .into_iter()
+            .filter(|change| change.kind == ChangeKind::Opened)
+            .filter(|change| change.tee_time.tee_time > now)
+            .filter(|change| filter.matches(&change.tee_time))
+            .take(MAX_ENTRIES)
+            .map(|change| TeeTimeChange {
+                tee_time: filter.for_party(&change.tee_time),
+                ..change
+            })
+            .collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn fetched_for(players: u32) -> TeeTimeChange {
+            TeeTimeChange {
+                id: "test-course/1".to_string(),
+                kind: ChangeKind::Opened,
+                seen: Utc::now(),
+                tee_time: TeeTime {
+                    course: "Test Course".to_string(),
+                    slug: "test-course".to_string(),
+                    tee_time: Utc::now() + chrono::Duration::days(1),
+                    timezone: GolfCourse::DEFAULT_TIMEZONE,
+                    price_per_player: 30.0,
+                    total_for_party: 30.0 * players as f64,
+                    players: 4,
+                    holes: Some(18),
+                    rate: None,
+                    lat: 39.1,
+                    lon: -94.6,
+                    book_url: format!("https://example.com/book?date=2026-10-20&players={}", players),
+                },
+                previous_price_per_player: None,
+            }
+        }
+
+        #[test]
+        fn entries_are_priced_for_the_feeds_party() {
+            let filter = FeedFilter {
+                slugs: ["test-course".to_string()].into(),
+                players: 3,
+                holes: None,
+                dates: None,
+            };
+
+            let entries = opened(vec![fetched_for(1)], &filter);
+
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].tee_time.total_for_party, 90.0);
+            assert_eq!(entries[0].tee_time.book_url, "https://example.com/book?date=2026-10-20&players=3");
+        }
+    }
+}
+
+pub mod live {
+    pub use super::*;
+
+    use axum::extract::ws::{Message, WebSocket};
+    use tokio::sync::broadcast::error::RecvError;
+
+    use crate::course_service::courses::{find_course, get_courses, Listing};
+    use crate::structs::{ClientMessage, ServerMessage, Subscription};
+    use feeds::FeedFilter;
+
+    /// One `/ws` connection: takes `subscribe` messages and pushes matching changes
+    /// from the poller, priced for the subscription's party, until the client goes away.
+    pub async fn session(mut socket: WebSocket, state: AppState) {
+        let mut changes = state.history.subscribe();
+        let mut filter: Option<FeedFilter> = None;
+
+        loop {
+            tokio::select! {
+                message = socket.recv() => {
+                    let Some(Ok(message)) = message else { break };
+
+                    let replies = match message {
+                        Message::Text(text) => handle_message(&state, &text, &mut filter),
+                        Message::Close(_) => break,
+                        // axum answers pings itself
+                        _ => Vec::new(),
+                    };
+                    for reply in replies {
+                        if send(&mut socket, &reply).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                change = changes.recv() => {
+                    let reply = match change {
+                        Ok(change) => match filter.as_ref().filter(|f| f.matches(&change.tee_time)) {
+                            Some(filter) => ServerMessage::Change(TeeTimeChange {
+                                tee_time: filter.for_party(&change.tee_time),
+                                ..change
+                            }),
+                            None => continue,
+                        },
+                        Err(RecvError::Lagged(missed)) => ServerMessage::Lagged { missed },
+                        Err(RecvError::Closed) => break,
+                    };
+                    if send(&mut socket, &reply).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    fn handle_message(state: &AppState, text: &str, filter: &mut Option<FeedFilter>) -> Vec<ServerMessage> {
+        let message = match serde_json::from_str::<ClientMessage>(text) {
+            Ok(message) => message,
+            Err(e) => return vec![ServerMessage::Error { error: format!("Invalid message: {}", e) }],
+        };
+
+        match message {
+            ClientMessage::Subscribe(subscription) => match subscribe(state, subscription) {
+                Ok(new_filter) => {
+                    let mut courses: Vec<String> = new_filter.slugs.iter().cloned().collect();
+                    courses.sort();
+
+                    let tee_times = state
+                        .history
+                        .current(&new_filter.slugs)
+                        .into_iter()
+                        .filter(|tt| new_filter.matches(tt))
+                        .map(|tt| new_filter.for_party(&tt))
+                        .collect();
+
+                    *filter = Some(new_filter);
+                    vec![ServerMessage::Subscribed { courses }, ServerMessage::Snapshot { tee_times }]
+                }
+                Err(error) => vec![ServerMessage::Error { error }],
+            },
+            ClientMessage::Unsubscribe => {
+                *filter = None;
+                Vec::new()
+            }
+        }
+    }
+
+    /// Resolves a subscription to the courses it covers, checked the same way as `/tee_times`.
+    fn subscribe(state: &AppState, subscription: Subscription) -> Result<FeedFilter, String> {
+        if subscription.courses.is_empty() && subscription.coords.is_none() {
+            return Err("subscribe needs courses, coords or both".to_string());
+        }
+
+        let catalog = state.courses();
+        let mut slugs: HashSet<String> = HashSet::new();
+
+        for slug in &subscription.courses {
+            let course = find_course(&catalog, slug).ok_or_else(|| format!("No course with slug {:?}", slug))?;
+            slugs.insert(course.slug.clone());
+        }
+
+        if let Some(coords) = subscription.coords {
+            coords.validate()?;
+            slugs.extend(get_courses(&catalog, Some(coords), Listing::All).into_iter().map(|c| c.slug.clone()));
+        }
+
+        Ok(FeedFilter {
+            slugs,
+            players: subscription.players,
+            holes: check_holes(subscription.holes)?,
+            dates: subscription.date.map(|date| vec![date]),
+        })
+    }
+
+    async fn send(socket: &mut WebSocket, message: &ServerMessage) -> Result<(), axum::Error> {
+        let text = serde_json::to_string(message).expect("server messages serialize to JSON");
+
+        socket.send(Message::Text(text)).await
+    }
+}